@@ -0,0 +1,23 @@
+fn main() {
+    #[cfg(feature = "capi")]
+    generate_header();
+}
+
+/// Writes the C header for `oronsay::capi` to `$OUT_DIR/oronsay.h`. The copy
+/// checked in as `include/oronsay.h` is only rewritten when
+/// `ORONSAY_UPDATE_HEADER` is set, so builds never touch the source tree.
+#[cfg(feature = "capi")]
+fn generate_header() {
+    use std::{env, path::Path};
+
+    let dir = env::var("CARGO_MANIFEST_DIR").expect("cargo sets CARGO_MANIFEST_DIR");
+    let out_dir = env::var("OUT_DIR").expect("cargo sets OUT_DIR");
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=ORONSAY_UPDATE_HEADER");
+    let bindings = cbindgen::generate(&dir).expect("cannot generate the C header");
+    bindings.write_to_file(Path::new(&out_dir).join("oronsay.h"));
+    if env::var_os("ORONSAY_UPDATE_HEADER").is_some() {
+        bindings.write_to_file(Path::new(&dir).join("include/oronsay.h"));
+    }
+}
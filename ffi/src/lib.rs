@@ -0,0 +1,4 @@
+//! Links `oronsay` into a shared and a static library. The exports come from
+//! `oronsay::capi`, `oronsay::python` and `oronsay::wasm`.
+
+pub use oronsay::*;
@@ -0,0 +1,164 @@
+#ifndef ORONSAY_H
+#define ORONSAY_H
+
+/* Generated by cbindgen from src/capi.rs with the capi feature; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of every call that takes a puzzle.
+ */
+typedef enum OronsayStatus {
+  ORONSAY_STATUS_OK = 0,
+  /**
+   * The puzzle has no solution.
+   */
+  ORONSAY_STATUS_NO_SOLUTION = 1,
+  /**
+   * The puzzle has more than one solution, so it cannot be rated.
+   */
+  ORONSAY_STATUS_NOT_UNIQUE = 2,
+  /**
+   * The puzzle holds a byte that is neither a digit nor `.`.
+   */
+  ORONSAY_STATUS_INVALID_PUZZLE = -1,
+  /**
+   * A required pointer was null.
+   */
+  ORONSAY_STATUS_NULL_POINTER = -2,
+} OronsayStatus;
+
+/**
+ * Logical techniques, from easiest to hardest.
+ */
+typedef enum OronsayTechnique {
+  ORONSAY_TECHNIQUE_FULL_HOUSE,
+  ORONSAY_TECHNIQUE_HIDDEN_SINGLE,
+  ORONSAY_TECHNIQUE_NAKED_SINGLE,
+  ORONSAY_TECHNIQUE_POINTING,
+  ORONSAY_TECHNIQUE_CLAIMING,
+  ORONSAY_TECHNIQUE_NAKED_PAIR,
+  ORONSAY_TECHNIQUE_HIDDEN_PAIR,
+  ORONSAY_TECHNIQUE_NAKED_TRIPLE,
+  ORONSAY_TECHNIQUE_X_WING,
+  /**
+   * No technique applies; the value comes from the solver.
+   */
+  ORONSAY_TECHNIQUE_SOLVER,
+} OronsayTechnique;
+
+/**
+ * A solver with its search state, for the C API in `include/oronsay.h`.
+ *
+ * Puzzles are 81 bytes in reading order with `1`-`9` for givens and `.` or
+ * `0` for empty cells; they need not be NUL-terminated. Solutions are
+ * written as 81 ASCII digits. A solver is not safe to share between
+ * threads, except for `oronsay_solve_batch`; create one per thread instead.
+ */
+typedef struct OronsaySolver OronsaySolver;
+
+typedef struct OronsayRating {
+  /**
+   * The hardest technique needed to solve the puzzle by hand.
+   */
+  enum OronsayTechnique hardest;
+  /**
+   * Deductions made, placements and eliminations alike.
+   */
+  uintptr_t steps;
+} OronsayRating;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates a solver that counts solutions up to `limit` (at least 1). Free it
+ * with `oronsay_solver_free`.
+ */
+struct OronsaySolver *oronsay_solver_new(uintptr_t limit);
+
+/**
+ * Frees a solver from `oronsay_solver_new`. Null is ignored.
+ *
+ * # Safety
+ *
+ * `solver` must be null or a pointer from `oronsay_solver_new` that has not
+ * been freed yet.
+ */
+void oronsay_solver_free(struct OronsaySolver *solver);
+
+/**
+ * Solves `puzzle` and writes the first solution found to `solution`.
+ *
+ * # Safety
+ *
+ * `solver` must come from `oronsay_solver_new`; `puzzle` must point to 81
+ * readable bytes and `solution` to 81 writable bytes.
+ */
+enum OronsayStatus oronsay_solve(struct OronsaySolver *solver,
+                                 const uint8_t *puzzle,
+                                 uint8_t *solution);
+
+/**
+ * Counts the solutions of `puzzle`, stopping at the solver's limit, and
+ * writes the count to `count`. A puzzle with no solution is `Ok` with a
+ * count of zero.
+ *
+ * # Safety
+ *
+ * `solver` must come from `oronsay_solver_new`; `puzzle` must point to 81
+ * readable bytes and `count` must be writable.
+ */
+enum OronsayStatus oronsay_count_solutions(struct OronsaySolver *solver,
+                                           const uint8_t *puzzle,
+                                           uintptr_t *count);
+
+/**
+ * Rates `puzzle` by the techniques needed to solve it by hand.
+ *
+ * # Safety
+ *
+ * `solver` must come from `oronsay_solver_new`; `puzzle` must point to 81
+ * readable bytes and `rating` must be writable.
+ */
+enum OronsayStatus oronsay_rate(struct OronsaySolver *solver,
+                                const uint8_t *puzzle,
+                                struct OronsayRating *rating);
+
+/**
+ * Solves `count` puzzles of 81 bytes each, packed back to back, on
+ * `num_threads` threads (at least 1), each with its own search state.
+ * Solutions are written to `solutions` in the same layout; a puzzle with no
+ * solution gets 81 `.` bytes. The number solved is written to `solved`;
+ * with a `limit` above 1, a puzzle found to have more than one solution gets
+ * its first one but is not counted. Stops before solving anything at the
+ * first invalid puzzle, and returns `InvalidPuzzle` if `81 * count` bytes
+ * cannot be addressed.
+ *
+ * # Safety
+ *
+ * `solver` must come from `oronsay_solver_new`; `puzzles` must point to
+ * `81 * count` readable bytes, `solutions` to `81 * count` writable bytes and
+ * `solved` must be writable.
+ */
+enum OronsayStatus oronsay_solve_batch(const struct OronsaySolver *solver,
+                                       const uint8_t *puzzles,
+                                       uintptr_t count,
+                                       uintptr_t num_threads,
+                                       uint8_t *solutions,
+                                       uintptr_t *solved);
+
+/**
+ * The name of `technique` as a static NUL-terminated string, e.g. "X-Wing".
+ */
+const char *oronsay_technique_name(enum OronsayTechnique technique);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ORONSAY_H */
@@ -0,0 +1,165 @@
+use alloc::{format, vec::Vec};
+
+use crate::error::{Error, Result};
+
+/// Which bytes of an input puzzle stand for an empty cell, and what to
+/// write for empty cells in the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Alphabet {
+    /// Bytes that are an empty cell.
+    empty: [bool; 256],
+    /// Bytes that may appear in a puzzle: the digits and the empty bytes.
+    allowed: [bool; 256],
+    output_empty: Option<u8>,
+}
+
+impl Default for Alphabet {
+    /// `.` and `0` are empty; anything else but `1`-`9` is an error.
+    fn default() -> Self {
+        Self::new(b".0").expect("default empty characters are not digits")
+    }
+}
+
+impl Alphabet {
+    /// Only the bytes in `empty` are empty cells.
+    pub fn new(empty: impl AsRef<[u8]>) -> Result<Self> {
+        let mut alphabet = Self {
+            empty: [false; 256],
+            allowed: [false; 256],
+            output_empty: None,
+        };
+        for byte in b'1'..=b'9' {
+            alphabet.allowed[byte as usize] = true;
+        }
+        for &byte in empty.as_ref() {
+            if (b'1'..=b'9').contains(&byte) {
+                return Err(Error::Format(format!("'{}' cannot mean an empty cell", byte as char)));
+            }
+            alphabet.empty[byte as usize] = true;
+            alphabet.allowed[byte as usize] = true;
+        }
+        Ok(alphabet)
+    }
+
+    /// Every byte but `1`-`9` is an empty cell, as solvers treat unchecked input.
+    pub fn permissive() -> Self {
+        let mut alphabet = Self::new([]).expect("no empty characters to check");
+        for byte in 0..=u8::MAX {
+            alphabet.empty[byte as usize] = !(b'1'..=b'9').contains(&byte);
+            alphabet.allowed[byte as usize] = true;
+        }
+        alphabet
+    }
+
+    /// Writes `byte` for every empty cell of the puzzles echoed to the output.
+    pub fn normalize_empty(mut self, byte: u8) -> Self {
+        self.output_empty = Some(byte);
+        self
+    }
+
+    pub fn is_empty(&self, byte: u8) -> bool {
+        self.empty[byte as usize]
+    }
+
+    /// Checks every cell of a puzzle on `line` (1-based) of the input.
+    pub fn validate(&self, line: usize, grid: &[u8]) -> Result<()> {
+        match grid.iter().position(|&byte| !self.allowed[byte as usize]) {
+            Some(index) => Err(Error::InvalidCharacter {
+                line,
+                column: index + 1,
+                byte: grid[index],
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Appends `grid` to `out`, empty cells replaced when normalising.
+    pub fn write(&self, grid: &[u8], out: &mut Vec<u8>) {
+        match self.output_empty {
+            Some(empty) => out.extend(grid.iter().map(|&byte| match self.is_empty(byte) {
+                true => empty,
+                false => byte,
+            })),
+            None => out.extend_from_slice(grid),
+        }
+    }
+
+    /// The byte written for a cell left empty by a solver that gave up.
+    pub fn output_empty(&self) -> u8 {
+        self.output_empty.unwrap_or(b'.')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::batch::parse_puzzles_with;
+
+    const PUZZLE: &[u8] = b"53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+    /// `PUZZLE` with `empty` for its empty cells.
+    fn with_empty(empty: u8) -> Vec<u8> {
+        PUZZLE.iter().map(|&byte| if byte == b'.' { empty } else { byte }).collect()
+    }
+
+    fn invalid(result: Result<impl core::fmt::Debug>) -> (usize, usize, u8) {
+        match result {
+            Err(Error::InvalidCharacter { line, column, byte }) => (line, column, byte),
+            other => panic!("expected an invalid character, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn default_accepts_dots_and_zeros_only() {
+        let alphabet = Alphabet::default();
+        assert!(alphabet.is_empty(b'.') && alphabet.is_empty(b'0') && !alphabet.is_empty(b'5'));
+        assert!(alphabet.validate(1, PUZZLE).is_ok());
+        assert!(alphabet.validate(1, &with_empty(b'0')).is_ok());
+        assert_eq!(invalid(alphabet.validate(7, b"12-45")), (7, 3, b'-'));
+    }
+
+    #[test]
+    fn custom_empties_replace_the_default_ones() {
+        let alphabet = Alphabet::new("-_").unwrap();
+        assert!(alphabet.is_empty(b'-') && alphabet.is_empty(b'_') && !alphabet.is_empty(b'.'));
+        let dashes = with_empty(b'-');
+        assert!(alphabet.validate(1, &dashes).is_ok());
+        assert_eq!(invalid(alphabet.validate(1, PUZZLE)), (1, 3, b'.'));
+        assert!(matches!(Alphabet::new("x5"), Err(Error::Format(message)) if message.contains("'5' cannot mean")));
+
+        let mut out = Vec::new();
+        alphabet.normalize_empty(b'.').write(&dashes, &mut out);
+        assert_eq!(out, PUZZLE);
+        assert_eq!(alphabet.output_empty(), b'.');
+        assert_eq!(alphabet.normalize_empty(b'0').output_empty(), b'0');
+    }
+
+    #[test]
+    fn bad_bytes_are_reported_by_line_and_column() {
+        let mut bad = PUZZLE.to_vec();
+        bad[40] = b'x';
+        let buffer = [PUZZLE, b"\n", PUZZLE, b"\r\n\n", &bad, b"\n"].concat();
+        assert_eq!(invalid(parse_puzzles_with(&buffer, &Alphabet::default())), (4, 41, b'x'));
+
+        // A header line is skipped but still counted
+        let buffer = [b"quizzes,solutions\n", PUZZLE, b"\n", &bad].concat();
+        assert_eq!(invalid(parse_puzzles_with(&buffer, &Alphabet::default())), (3, 41, b'x'));
+        assert_eq!(invalid(parse_puzzles_with(&buffer, &Alphabet::new("x").unwrap())), (2, 3, b'.'));
+        assert_eq!(parse_puzzles_with(&buffer, &Alphabet::new(".x").unwrap()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn permissive_treats_every_other_byte_as_empty() {
+        let alphabet = Alphabet::permissive();
+        assert!([b'.', b'0', b'x', b' ', 0xff].iter().all(|&byte| alphabet.is_empty(byte)));
+        assert!((b'1'..=b'9').all(|byte| !alphabet.is_empty(byte)));
+        let noisy = vec![b'?'; 81];
+        assert!(alphabet.validate(1, &noisy).is_ok());
+
+        let mut out = Vec::new();
+        alphabet.normalize_empty(b'.').write(b"1?3 5", &mut out);
+        assert_eq!(out, b"1.3.5");
+    }
+}
@@ -0,0 +1,253 @@
+use alloc::{format, vec::Vec};
+#[cfg(feature = "std")]
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::Instant,
+};
+
+use crate::{
+    alphabet::Alphabet,
+    error::{Error, Result},
+    sudoku::{Puzzle, Sudoku, N_CELLS},
+};
+#[cfg(feature = "std")]
+use crate::{
+    solver::{SolutionInfo, Solver},
+    types::ChunkStats,
+};
+
+#[cfg(feature = "std")]
+/// Number of puzzles handed to a worker at a time.
+const BATCH_CHUNK: usize = 256;
+
+#[cfg(feature = "std")]
+/// Results of `solve_batch`, in the same order as the input puzzles.
+pub struct BatchReport {
+    pub solutions: Vec<Option<SolutionInfo>>,
+    pub stats: ChunkStats,
+}
+
+#[cfg(feature = "std")]
+/// Solves in-memory puzzles on `num_threads` threads, each with its own solver state.
+pub fn solve_batch<S: Solver>(solver: &S, puzzles: &[Sudoku], num_threads: usize) -> BatchReport {
+    let solved = for_each_chunk(solver, puzzles, num_threads, |chunk, state| {
+        solve_chunk(solver, chunk, state)
+    });
+
+    let mut report = BatchReport {
+        solutions: Vec::with_capacity(puzzles.len()),
+        stats: ChunkStats::default(),
+    };
+    for (solutions, stats) in solved {
+        report.solutions.extend(solutions);
+        report.stats.add(&stats);
+    }
+    report
+}
+
+#[cfg(feature = "std")]
+/// Runs `f` over chunks of `puzzles` on `num_threads` threads, each with its
+/// own solver state, and returns the results in chunk order.
+pub(crate) fn for_each_chunk<S, T, F>(
+    solver: &S,
+    puzzles: &[Sudoku],
+    num_threads: usize,
+    f: F,
+) -> Vec<T>
+where
+    S: Solver,
+    T: Send,
+    F: Fn(&[Sudoku], &mut S::State) -> T + Sync,
+{
+    map_chunks(puzzles, num_threads, || solver.make_state(), f)
+}
+
+#[cfg(feature = "std")]
+/// Runs `f` over chunks of `items` on `num_threads` threads, each with its own
+/// state from `make_state`, and returns the results in chunk order.
+pub(crate) fn map_chunks<I, St, T, G, F>(
+    items: &[I],
+    num_threads: usize,
+    make_state: G,
+    f: F,
+) -> Vec<T>
+where
+    I: Sync,
+    T: Send,
+    G: Fn() -> St + Sync,
+    F: Fn(&[I], &mut St) -> T + Sync,
+{
+    let chunks = items.chunks(BATCH_CHUNK).collect::<Vec<_>>();
+    let next_chunk = AtomicUsize::new(0);
+
+    let mut results = thread::scope(|scope| {
+        let handles = (0..num_threads.max(1))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut state = make_state();
+                    let mut results = Vec::new();
+                    loop {
+                        let id = next_chunk.fetch_add(1, Ordering::Relaxed);
+                        let Some(chunk) = chunks.get(id) else {
+                            break;
+                        };
+                        results.push((id, f(chunk, &mut state)));
+                    }
+                    results
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect::<Vec<_>>()
+    });
+    results.sort_unstable_by_key(|(id, _)| *id);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(feature = "std")]
+/// Like `solve_batch`, for a buffer holding one puzzle per line.
+pub fn solve_batch_bytes<S: Solver>(
+    solver: &S,
+    buffer: &[u8],
+    num_threads: usize,
+) -> Result<BatchReport> {
+    let puzzles = parse_puzzles(buffer)?;
+    Ok(solve_batch(solver, &puzzles, num_threads))
+}
+
+/// Parses a buffer with one puzzle per line. Like the `Reader`, a first line
+/// whose length differs from the second is treated as a header and skipped.
+/// Empty cells are `.` or `0`.
+pub fn parse_puzzles(buffer: &[u8]) -> Result<Vec<Sudoku>> {
+    parse_puzzles_with(buffer, &Alphabet::default())
+}
+
+/// Like `parse_puzzles`, with `alphabet` deciding which bytes are empty cells.
+pub fn parse_puzzles_with(buffer: &[u8], alphabet: &Alphabet) -> Result<Vec<Sudoku>> {
+    let lines = buffer
+        .split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .collect::<Vec<_>>();
+    let skip = match lines.as_slice() {
+        [first, second, ..] if !second.is_empty() && first.len() != second.len() => 1,
+        _ => 0,
+    };
+
+    let mut puzzles = Vec::new();
+    for (index, line) in lines.into_iter().enumerate().skip(skip) {
+        if line.is_empty() {
+            continue;
+        }
+        let grid = line.get(..N_CELLS).ok_or_else(|| {
+            Error::Format(format!(
+                "line {} is {} bytes long, expected at least {}",
+                index + 1,
+                line.len(),
+                N_CELLS
+            ))
+        })?;
+        alphabet.validate(index + 1, grid)?;
+        puzzles.push(Puzzle::new(grid).sudoku());
+    }
+    Ok(puzzles)
+}
+
+#[cfg(feature = "std")]
+fn solve_chunk<S: Solver>(
+    solver: &S,
+    puzzles: &[Sudoku],
+    state: &mut S::State,
+) -> (Vec<Option<SolutionInfo>>, ChunkStats) {
+    let start = Instant::now();
+    let mut stats = ChunkStats::default();
+    let mut solutions = Vec::with_capacity(puzzles.len());
+
+    for sudoku in puzzles {
+        let solution = solver.solve(&Puzzle::new(&sudoku.grid), state);
+        stats.puzzles += 1;
+        if let Some(solution) = solution.as_ref() {
+            stats.solutions += 1;
+            stats.guesses += solution.guesses;
+            if solution.guesses == 0 {
+                stats.no_guesses += 1;
+            }
+        }
+        solutions.push(solution);
+    }
+
+    stats.chunks += 1;
+    stats.elapsed = start.elapsed();
+    (solutions, stats)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::solver::SolverBasic;
+
+    const EASY: &[u8] = include_bytes!("../corpora/easy.txt");
+    const THREADS: [usize; 5] = [0, 1, 2, 3, 16];
+
+    #[test]
+    fn parse_puzzles_keeps_line_order() {
+        let puzzles = parse_puzzles(EASY).unwrap();
+        assert_eq!(puzzles.len(), 500);
+        for (sudoku, line) in puzzles.iter().zip(EASY.split(|&byte| byte == b'\n')) {
+            assert_eq!(&sudoku.grid[..], &line[..N_CELLS]);
+        }
+        let with_header = [b"puzzle\n", EASY].concat();
+        assert_eq!(parse_puzzles_with(&with_header, &Alphabet::default()).unwrap(), puzzles);
+    }
+
+    #[test]
+    fn map_chunks_returns_chunks_in_input_order() {
+        // Enough items for several chunks, then fewer items than threads
+        for items in [(0..1000).collect::<Vec<_>>(), vec![7, 8, 9]] {
+            for threads in THREADS {
+                let results = map_chunks(&items, threads, || (), |chunk, _| chunk.to_vec());
+                assert_eq!(results.len(), items.len().div_ceil(BATCH_CHUNK));
+                assert_eq!(results.concat(), items, "{} threads", threads);
+            }
+        }
+    }
+
+    #[test]
+    fn solve_batch_matches_solutions_to_their_puzzles() {
+        let puzzles = parse_puzzles(EASY).unwrap();
+        let solver = SolverBasic::new(1, true);
+        for (puzzles, threads) in [(&puzzles[..], THREADS.to_vec()), (&puzzles[..3], vec![4, 16])] {
+            for threads in threads {
+                let report = solve_batch(&solver, puzzles, threads);
+                assert_eq!(report.solutions.len(), puzzles.len());
+                assert_eq!(report.stats.puzzles, puzzles.len());
+                for (sudoku, solution) in puzzles.iter().zip(&report.solutions) {
+                    assert!(solution.as_ref().unwrap().sudoku.solves(sudoku), "{} threads", threads);
+                }
+            }
+        }
+
+        let report = solve_batch_bytes(&solver, &EASY[..(N_CELLS + 1) * 10], 3).unwrap();
+        assert_eq!(report.solutions.len(), 10);
+        for (sudoku, solution) in puzzles.iter().zip(&report.solutions) {
+            assert!(solution.as_ref().unwrap().sudoku.solves(sudoku));
+        }
+    }
+
+    #[test]
+    fn empty_input_gives_empty_results() {
+        let solver = SolverBasic::new(1, true);
+        for threads in THREADS {
+            let report = solve_batch(&solver, &[], threads);
+            assert!(report.solutions.is_empty());
+            assert_eq!((report.stats.puzzles, report.stats.chunks), (0, 0));
+            assert!(map_chunks(&[] as &[u8], threads, || (), |chunk, _| chunk.len()).is_empty());
+        }
+        assert!(parse_puzzles(b"").unwrap().is_empty());
+        assert!(parse_puzzles(b"\n\r\n").unwrap().is_empty());
+        assert!(solve_batch_bytes(&solver, b"", 2).unwrap().solutions.is_empty());
+    }
+}
@@ -0,0 +1,180 @@
+use std::{fs, path::Path, time::Instant};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "serde")]
+use crate::error::Error;
+use crate::{
+    batch::{parse_puzzles, solve_batch},
+    error::Result,
+    solver::Solver,
+    sudoku::Sudoku,
+};
+
+/// Small generated test sets, graded by how many guesses `SolverBasic` needs.
+const BUILTIN: &[(&str, &[u8])] = &[
+    ("easy", include_bytes!("../corpora/easy.txt")),
+    ("medium", include_bytes!("../corpora/medium.txt")),
+    ("hard", include_bytes!("../corpora/hard.txt")),
+];
+
+pub struct Corpus {
+    pub name: String,
+    pub puzzles: Vec<Sudoku>,
+}
+
+impl Corpus {
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN.iter().map(|(name, _)| *name)
+    }
+
+    pub fn builtin(name: &str) -> Option<Result<Corpus>> {
+        let (name, data) = BUILTIN.iter().find(|(builtin, _)| *builtin == name)?;
+        Some(parse_puzzles(data).map(|puzzles| Corpus {
+            name: name.to_string(),
+            puzzles,
+        }))
+    }
+
+    /// Loads a built-in corpus by name, or else a puzzle file by path.
+    pub fn load(name_or_path: &str) -> Result<Corpus> {
+        if let Some(corpus) = Self::builtin(name_or_path) {
+            return corpus;
+        }
+        let path = Path::new(name_or_path);
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| name_or_path.to_string());
+        Ok(Corpus {
+            name,
+            puzzles: parse_puzzles(&fs::read(path)?)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BenchConfig {
+    /// Untimed runs before measuring.
+    pub warmup: usize,
+    pub trials: usize,
+    pub threads: usize,
+}
+
+/// Mean of the trials with the half-width of its 95% confidence interval.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Estimate {
+    pub mean: f64,
+    pub ci95: f64,
+}
+
+impl Estimate {
+    fn from_samples(samples: &[f64]) -> Self {
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        if samples.len() < 2 {
+            return Self { mean, ci95: 0.0 };
+        }
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        Self {
+            mean,
+            ci95: t_quantile(samples.len() - 1) * (variance / n).sqrt(),
+        }
+    }
+}
+
+/// Two-sided 95% quantile of Student's t distribution.
+fn t_quantile(degrees: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179,
+        2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064,
+        2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+    ];
+    TABLE.get(degrees.wrapping_sub(1)).copied().unwrap_or(1.960)
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BenchResult {
+    pub solver: String,
+    pub corpus: String,
+    pub threads: usize,
+    pub puzzles: usize,
+    pub trials: usize,
+    pub puzzles_per_sec: Estimate,
+    pub ns_per_puzzle: Estimate,
+    pub guesses_per_puzzle: f64,
+}
+
+impl BenchResult {
+    fn matches(&self, other: &BenchResult) -> bool {
+        self.solver == other.solver && self.corpus == other.corpus && self.threads == other.threads
+    }
+}
+
+/// Times `solver` over the puzzles in memory, so reading and writing are not measured.
+pub fn bench<S: Solver>(
+    solver_name: &str,
+    solver: &S,
+    corpus: &Corpus,
+    config: &BenchConfig,
+) -> BenchResult {
+    for _ in 0..config.warmup {
+        solve_batch(solver, &corpus.puzzles, config.threads);
+    }
+
+    let puzzles = corpus.puzzles.len().max(1) as f64;
+    let mut rates = Vec::with_capacity(config.trials);
+    let mut times = Vec::with_capacity(config.trials);
+    let mut guesses = 0;
+    for _ in 0..config.trials.max(1) {
+        let start = Instant::now();
+        let report = solve_batch(solver, &corpus.puzzles, config.threads);
+        let elapsed = start.elapsed().as_secs_f64();
+
+        rates.push(puzzles / elapsed);
+        times.push(elapsed * 1e9 / puzzles);
+        guesses = report.stats.guesses;
+    }
+
+    BenchResult {
+        solver: solver_name.to_string(),
+        corpus: corpus.name.clone(),
+        threads: config.threads,
+        puzzles: corpus.puzzles.len(),
+        trials: times.len(),
+        puzzles_per_sec: Estimate::from_samples(&rates),
+        ns_per_puzzle: Estimate::from_samples(&times),
+        guesses_per_puzzle: guesses as f64 / puzzles,
+    }
+}
+
+/// Results saved from an earlier run, to compare against.
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Baseline {
+    pub results: Vec<BenchResult>,
+}
+
+impl Baseline {
+    #[cfg(feature = "serde")]
+    pub fn load(path: impl AsRef<Path>) -> Result<Baseline> {
+        let data = fs::read(path)?;
+        serde_json::from_slice(&data).map_err(|err| Error::Format(format!("baseline: {}", err)))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(|err| Error::Format(format!("baseline: {}", err)))?;
+        Ok(fs::write(path, data)?)
+    }
+
+    /// Relative change in ns/puzzle against the matching baseline entry, e.g. -0.05 for 5% faster.
+    pub fn change(&self, result: &BenchResult) -> Option<f64> {
+        let base = self.results.iter().find(|base| base.matches(result))?;
+        Some(result.ns_per_puzzle.mean / base.ns_per_puzzle.mean - 1.0)
+    }
+}
@@ -0,0 +1,300 @@
+use alloc::{format, string::String, vec::Vec};
+
+use crate::{
+    error::{Error, Result},
+    hint::Action,
+    sudoku::{Sudoku, N_CELLS},
+};
+
+/// Bit `d - 1` is set when digit `d` is still possible.
+pub type Mask = u16;
+pub(crate) const ALL: Mask = 0x1ff;
+
+pub(crate) fn row_of(cell: usize) -> usize {
+    cell / 9
+}
+
+pub(crate) fn col_of(cell: usize) -> usize {
+    cell % 9
+}
+
+pub(crate) fn box_of(cell: usize) -> usize {
+    cell / 27 * 3 + cell % 9 / 3
+}
+
+/// Cell `j` of row `i`, column `i` or box `i`, boxes read left to right, top to bottom.
+pub(crate) fn row_cell(i: usize, j: usize) -> usize {
+    i * 9 + j
+}
+
+pub(crate) fn col_cell(i: usize, j: usize) -> usize {
+    j * 9 + i
+}
+
+pub(crate) fn box_cell(i: usize, j: usize) -> usize {
+    (i / 3 * 3 + j / 3) * 9 + i % 3 * 3 + j % 3
+}
+
+/// The 27 units of a standard grid: rows, then columns, then boxes.
+pub(crate) fn units() -> [[usize; 9]; 27] {
+    core::array::from_fn(|index| {
+        let cell = [row_cell, col_cell, box_cell][index / 9];
+        core::array::from_fn(|j| cell(index % 9, j))
+    })
+}
+
+/// Whether two different cells share a row, column or box.
+pub(crate) fn sees(a: usize, b: usize) -> bool {
+    a != b && (row_of(a) == row_of(b) || col_of(a) == col_of(b) || box_of(a) == box_of(b))
+}
+
+/// Cell index from `r<row>c<col>` notation, both 1-based.
+pub(crate) fn parse_cell(name: &str) -> Option<usize> {
+    let (row, col) = name.strip_prefix(['r', 'R'])?.split_once(['c', 'C'])?;
+    let (row, col) = (row.parse::<usize>().ok()?, col.parse::<usize>().ok()?);
+    ((1..=9).contains(&row) && (1..=9).contains(&col)).then(|| (row - 1) * 9 + col - 1)
+}
+
+/// The digit (1-9) held by an ASCII cell, if any.
+pub(crate) fn value_of(byte: u8) -> Option<u8> {
+    (b'1'..=b'9').contains(&byte).then(|| byte - b'0')
+}
+
+pub(crate) fn bit(value: u8) -> Mask {
+    1 << (value - 1)
+}
+
+/// Pencil marks: the digits still possible in each cell. A cell down to one
+/// candidate counts as filled in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CandidateGrid {
+    masks: [Mask; N_CELLS],
+}
+
+impl Default for CandidateGrid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CandidateGrid {
+    /// Every digit possible in every cell.
+    pub fn new() -> Self {
+        Self {
+            masks: [ALL; N_CELLS],
+        }
+    }
+
+    /// Filled cells keep only their digit, which is removed from their peers.
+    pub fn from_sudoku(sudoku: &Sudoku) -> Self {
+        let mut grid = Self::new();
+        for (cell, &byte) in sudoku.grid.iter().enumerate() {
+            if let Some(value) = value_of(byte) {
+                grid.place(cell, value);
+            }
+        }
+        grid
+    }
+
+    pub fn mask(&self, cell: usize) -> Mask {
+        self.masks[cell]
+    }
+
+    pub fn contains(&self, cell: usize, value: u8) -> bool {
+        self.masks[cell] & bit(value) != 0
+    }
+
+    pub fn candidates(&self, cell: usize) -> impl Iterator<Item = u8> {
+        let mask = self.masks[cell];
+        (1..=9).filter(move |&value| mask & bit(value) != 0)
+    }
+
+    /// The digit of a cell down to a single candidate.
+    pub fn value(&self, cell: usize) -> Option<u8> {
+        match self.masks[cell].count_ones() {
+            1 => Some(self.masks[cell].trailing_zeros() as u8 + 1),
+            _ => None,
+        }
+    }
+
+    /// Removes one candidate and returns whether it was there.
+    pub fn eliminate(&mut self, cell: usize, value: u8) -> bool {
+        let present = self.contains(cell, value);
+        self.masks[cell] &= !bit(value);
+        present
+    }
+
+    /// Fills `cell` with `value` and removes `value` from its peers.
+    pub fn place(&mut self, cell: usize, value: u8) {
+        for peer in 0..N_CELLS {
+            if sees(cell, peer) {
+                self.masks[peer] &= !bit(value);
+            }
+        }
+        self.masks[cell] = bit(value);
+    }
+
+    /// Applies a hint's placement or eliminations.
+    pub fn apply(&mut self, action: &Action) {
+        match action {
+            Action::Place { cell, value } => self.place(*cell, *value),
+            Action::Eliminate(eliminations) => {
+                for &(cell, value) in eliminations {
+                    self.eliminate(cell, value);
+                }
+            }
+        }
+    }
+
+    /// Cells down to one candidate become digits, the rest are left empty.
+    pub fn to_sudoku(&self) -> Sudoku {
+        let mut grid = [b'.'; N_CELLS];
+        for (cell, byte) in grid.iter_mut().enumerate() {
+            if let Some(value) = self.value(cell) {
+                *byte = b'0' + value;
+            }
+        }
+        Sudoku::new(grid)
+    }
+
+    /// Parses the 729 character format: nine characters per cell, each either
+    /// the digit for its position or `0`/`.` when that digit is ruled out.
+    /// Whitespace is ignored, so the string may be split over lines.
+    pub fn parse(text: &str) -> Result<Self> {
+        let chars = text
+            .bytes()
+            .filter(|byte| !byte.is_ascii_whitespace())
+            .collect::<Vec<_>>();
+        if chars.len() != N_CELLS * 9 {
+            return Err(Error::Format(format!(
+                "candidate grid is {} characters long, expected {}",
+                chars.len(),
+                N_CELLS * 9
+            )));
+        }
+
+        let mut masks = [0; N_CELLS];
+        for (position, &byte) in chars.iter().enumerate() {
+            let (cell, value) = (position / 9, (position % 9) as u8 + 1);
+            match byte {
+                b'0' | b'.' => {}
+                _ if byte == b'0' + value => masks[cell] |= bit(value),
+                _ => {
+                    return Err(Error::Format(format!(
+                        "unexpected '{}' at position {} of candidate grid, expected '{}' or '.'",
+                        byte as char,
+                        position + 1,
+                        value
+                    )))
+                }
+            }
+        }
+        Ok(Self { masks })
+    }
+
+    /// The 729 character format read by `parse`, with `.` for ruled out digits.
+    pub fn to_candidate_string(&self) -> String {
+        let mut result = String::with_capacity(N_CELLS * 9);
+        for cell in 0..N_CELLS {
+            for value in 1..=9 {
+                result.push(match self.contains(cell, value) {
+                    true => (b'0' + value) as char,
+                    false => '.',
+                });
+            }
+        }
+        result
+    }
+
+    /// Pencil-mark diagram: each cell as a 3x3 block of its candidates, in the
+    /// style of `Sudoku::pretty`.
+    pub fn pretty(&self) -> String {
+        let mut result = String::new();
+        result.push_str("┌─────────────┬─────────────┬─────────────┐\n");
+        for row in 0..9 {
+            if row % 3 == 0 && row != 0 {
+                result.push_str("├─────────────┼─────────────┼─────────────┤\n");
+            } else if row != 0 {
+                result.push_str("│             │             │             │\n");
+            }
+            for line in 0..3 {
+                for col in 0..9 {
+                    if col % 3 == 0 {
+                        result.push_str("│ ");
+                    }
+                    for value in line * 3 + 1..=line * 3 + 3 {
+                        result.push(match self.contains(row * 9 + col, value as u8) {
+                            true => (b'0' + value as u8) as char,
+                            false => '.',
+                        });
+                    }
+                    result.push(' ');
+                }
+                result.push_str("│\n");
+            }
+        }
+        result.push_str("└─────────────┴─────────────┴─────────────┘\n");
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &[u8; N_CELLS] =
+        b"53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+    #[test]
+    fn units_cover_each_cell_once_per_kind() {
+        let units = units();
+        for kind in units.chunks(9) {
+            let mut seen = [0; N_CELLS];
+            kind.iter().flatten().for_each(|&cell| seen[cell] += 1);
+            assert!(seen.iter().all(|&count| count == 1));
+        }
+        assert_eq!(units[9 + 2], [2, 11, 20, 29, 38, 47, 56, 65, 74]);
+        assert_eq!(units[18 + 4], [30, 31, 32, 39, 40, 41, 48, 49, 50]);
+        for (index, unit) in units.iter().enumerate() {
+            let of = [row_of, col_of, box_of][index / 9];
+            assert!(unit.iter().all(|&cell| of(cell) == index % 9));
+            assert!(unit.iter().all(|&a| unit.iter().all(|&b| sees(a, b) == (a != b))));
+        }
+        assert_eq!((0..N_CELLS).filter(|&peer| sees(40, peer)).count(), 20);
+    }
+
+    #[test]
+    fn candidate_strings_round_trip() {
+        let mut grid = CandidateGrid::from_sudoku(&Sudoku::new(*PUZZLE));
+        grid.eliminate(2, 1);
+        grid.eliminate(78, 1);
+        let text = grid.to_candidate_string();
+        assert_eq!(text.len(), N_CELLS * 9);
+        assert_eq!(&text[..18], "....5......3......");
+        assert_eq!(CandidateGrid::parse(&text).unwrap(), grid);
+
+        // Zeros for ruled out digits and whitespace between cells read the same
+        let spaced = text
+            .replace('.', "0")
+            .as_bytes()
+            .chunks(81)
+            .map(|line| String::from_utf8_lossy(line) + "\n")
+            .collect::<String>();
+        assert_eq!(CandidateGrid::parse(&spaced).unwrap(), grid);
+        assert_eq!(CandidateGrid::parse(&CandidateGrid::new().to_candidate_string()).unwrap(), CandidateGrid::new());
+        let filled = grid.to_sudoku();
+        assert!(PUZZLE.iter().zip(filled.grid).all(|(&given, value)| given == b'.' || given == value));
+    }
+
+    #[test]
+    fn parse_rejects_bad_candidate_strings() {
+        let text = CandidateGrid::new().to_candidate_string();
+        let message = |text: &str| match CandidateGrid::parse(text) {
+            Err(Error::Format(message)) => message,
+            other => panic!("expected a format error, got {:?}", other),
+        };
+        assert!(message(&text[1..]).contains("728 characters long"));
+        let misplaced = format!("2{}", &text[1..]);
+        assert!(message(&misplaced).contains("unexpected '2' at position 1"));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::sudoku::{Sudoku, N_CELLS};
+
+type Row = [u8; 9];
+
+/// The 6 orderings of three items, used for bands, stacks and the lines within them.
+const PERMS3: [[usize; 3]; 6] = [
+    [0, 1, 2],
+    [0, 2, 1],
+    [1, 0, 2],
+    [1, 2, 0],
+    [2, 0, 1],
+    [2, 1, 0],
+];
+
+/// Branch-and-bound search for the smallest grid, building it one row at a time.
+///
+/// The column order and transposition are fixed by the caller; rows are chosen
+/// band by band. Digits are relabelled in order of first appearance, which is
+/// the best relabelling for any fixed cell order.
+struct Search {
+    best: [Row; 9],
+}
+
+impl Search {
+    fn new() -> Self {
+        Self {
+            best: [[u8::MAX; 9]; 9],
+        }
+    }
+
+    /// Relabels `row` under `cols`, extending `labels` with any new digits.
+    fn relabel(row: &Row, cols: &[usize; 9], labels: &mut [u8; 10], next_label: &mut u8) -> Row {
+        let mut out = [0; 9];
+        for (out, &col) in out.iter_mut().zip(cols) {
+            let digit = row[col] as usize;
+            if digit != 0 {
+                if labels[digit] == 0 {
+                    labels[digit] = *next_label;
+                    *next_label += 1;
+                }
+                *out = labels[digit];
+            }
+        }
+        out
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn rows(
+        &mut self,
+        grid: &[Row; 9],
+        cols: &[usize; 9],
+        position: usize,
+        bands_used: u8,
+        band: usize,
+        rows_used: u8,
+        labels: [u8; 10],
+        next_label: u8,
+    ) {
+        if position == 9 {
+            return;
+        }
+
+        // At the start of a band any unused band may follow, otherwise any
+        // unused row of the current band
+        for (b, r) in (0..3).flat_map(|b| (0..3).map(move |r| (b, r))) {
+            let allowed = match position % 3 {
+                0 => bands_used & (1 << b) == 0,
+                _ => b == band && rows_used & (1 << r) == 0,
+            };
+            if !allowed {
+                continue;
+            }
+
+            let mut labels = labels;
+            let mut next_label = next_label;
+            let row = Self::relabel(&grid[b * 3 + r], cols, &mut labels, &mut next_label);
+
+            match row.cmp(&self.best[position]) {
+                core::cmp::Ordering::Greater => continue,
+                core::cmp::Ordering::Less => {
+                    // Any completion of this prefix beats the old best
+                    self.best[position] = row;
+                    for best in self.best[position + 1..].iter_mut() {
+                        *best = [u8::MAX; 9];
+                    }
+                }
+                core::cmp::Ordering::Equal => {}
+            }
+
+            let (bands_used, rows_used) = match position % 3 {
+                0 => (bands_used | 1 << b, 1 << r),
+                _ => (bands_used, rows_used | 1 << r),
+            };
+            self.rows(
+                grid,
+                cols,
+                position + 1,
+                bands_used,
+                b,
+                rows_used,
+                labels,
+                next_label,
+            );
+        }
+    }
+}
+
+impl Sudoku {
+    /// Smallest equivalent grid under relabelling of digits, row and column
+    /// permutations within bands and stacks, band and stack permutations and
+    /// transposition. Empty cells are `.` and sort before every digit.
+    pub fn canonical(&self) -> Sudoku {
+        let mut grid = [[0u8; 9]; 9];
+        for (cell, &value) in self.grid.iter().enumerate() {
+            grid[cell / 9][cell % 9] = match value {
+                b'1'..=b'9' => value - b'0',
+                _ => 0,
+            };
+        }
+        let mut transposed = [[0u8; 9]; 9];
+        for (row, values) in grid.iter().enumerate() {
+            for (col, &value) in values.iter().enumerate() {
+                transposed[col][row] = value;
+            }
+        }
+
+        let mut search = Search::new();
+        for grid in [&grid, &transposed] {
+            for stacks in &PERMS3 {
+                for cols0 in &PERMS3 {
+                    for cols1 in &PERMS3 {
+                        for cols2 in &PERMS3 {
+                            let within = [cols0, cols1, cols2];
+                            let mut cols = [0; 9];
+                            for (i, col) in cols.iter_mut().enumerate() {
+                                let stack = stacks[i / 3];
+                                *col = stack * 3 + within[i / 3][i % 3];
+                            }
+                            search.rows(grid, &cols, 0, 0, 0, 0, [0; 10], 1);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut canonical = [b'.'; N_CELLS];
+        for (cell, value) in canonical.iter_mut().enumerate() {
+            let label = search.best[cell / 9][cell % 9];
+            if label != 0 {
+                *value = b'0' + label;
+            }
+        }
+        Sudoku::new(canonical)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::transform::Transform;
+
+    use super::*;
+
+    const PUZZLE: &[u8; N_CELLS] =
+        b"53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+    #[test]
+    fn isomorphs_share_a_canonical_form() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let puzzle = Sudoku::new(*PUZZLE);
+        let canonical = puzzle.canonical();
+        assert_eq!(canonical.canonical(), canonical);
+        assert_eq!(canonical.grid.iter().filter(|&&value| value == b'.').count(), 51);
+        for _ in 0..100 {
+            assert_eq!(Transform::random(&mut rng).apply(&puzzle).canonical(), canonical);
+        }
+
+        let mut other = *PUZZLE;
+        other.swap(0, 2);
+        assert_ne!(Sudoku::new(other).canonical(), canonical);
+    }
+}
@@ -0,0 +1,411 @@
+use std::{ffi::c_char, ptr, slice};
+
+use crate::{
+    alphabet::Alphabet,
+    batch::solve_batch,
+    hint::{rate, Technique},
+    solver::{Solver, SolverBasic},
+    sudoku::{Puzzle, Sudoku, N_CELLS},
+};
+
+/// Result of every call that takes a puzzle.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OronsayStatus {
+    Ok = 0,
+    /// The puzzle has no solution.
+    NoSolution = 1,
+    /// The puzzle has more than one solution, so it cannot be rated.
+    NotUnique = 2,
+    /// The puzzle holds a byte that is neither a digit nor `.`.
+    InvalidPuzzle = -1,
+    /// A required pointer was null.
+    NullPointer = -2,
+}
+
+/// Logical techniques, from easiest to hardest.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OronsayTechnique {
+    FullHouse,
+    HiddenSingle,
+    NakedSingle,
+    Pointing,
+    Claiming,
+    NakedPair,
+    HiddenPair,
+    NakedTriple,
+    XWing,
+    /// No technique applies; the value comes from the solver.
+    Solver,
+}
+
+impl From<Technique> for OronsayTechnique {
+    fn from(technique: Technique) -> Self {
+        match technique {
+            Technique::FullHouse => OronsayTechnique::FullHouse,
+            Technique::HiddenSingle => OronsayTechnique::HiddenSingle,
+            Technique::NakedSingle => OronsayTechnique::NakedSingle,
+            Technique::Pointing => OronsayTechnique::Pointing,
+            Technique::Claiming => OronsayTechnique::Claiming,
+            Technique::NakedPair => OronsayTechnique::NakedPair,
+            Technique::HiddenPair => OronsayTechnique::HiddenPair,
+            Technique::NakedTriple => OronsayTechnique::NakedTriple,
+            Technique::XWing => OronsayTechnique::XWing,
+            Technique::Solver => OronsayTechnique::Solver,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OronsayRating {
+    /// The hardest technique needed to solve the puzzle by hand.
+    pub hardest: OronsayTechnique,
+    /// Deductions made, placements and eliminations alike.
+    pub steps: usize,
+}
+
+/// A solver with its search state, for the C API in `include/oronsay.h`.
+///
+/// Puzzles are 81 bytes in reading order with `1`-`9` for givens and `.` or
+/// `0` for empty cells; they need not be NUL-terminated. Solutions are
+/// written as 81 ASCII digits. A solver is not safe to share between
+/// threads, except for `oronsay_solve_batch`; create one per thread instead.
+pub struct OronsaySolver {
+    solver: SolverBasic,
+    /// Looks for two solutions, as rating needs.
+    rater: SolverBasic,
+    state: <SolverBasic as Solver>::State,
+}
+
+/// Creates a solver that counts solutions up to `limit` (at least 1). Free it
+/// with `oronsay_solver_free`.
+#[no_mangle]
+pub extern "C" fn oronsay_solver_new(limit: usize) -> *mut OronsaySolver {
+    let solver = SolverBasic::new(limit.max(1), true);
+    Box::into_raw(Box::new(OronsaySolver {
+        state: solver.make_state(),
+        rater: SolverBasic::new(2, true),
+        solver,
+    }))
+}
+
+/// Frees a solver from `oronsay_solver_new`. Null is ignored.
+///
+/// # Safety
+///
+/// `solver` must be null or a pointer from `oronsay_solver_new` that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn oronsay_solver_free(solver: *mut OronsaySolver) {
+    if !solver.is_null() {
+        drop(Box::from_raw(solver));
+    }
+}
+
+/// The puzzle behind `puzzle`, if it is valid.
+///
+/// # Safety
+///
+/// `puzzle` must be null or point to 81 readable bytes.
+unsafe fn read_puzzle(puzzle: *const u8) -> Result<Sudoku, OronsayStatus> {
+    if puzzle.is_null() {
+        return Err(OronsayStatus::NullPointer);
+    }
+    let grid = slice::from_raw_parts(puzzle, N_CELLS);
+    Alphabet::default()
+        .validate(1, grid)
+        .map_err(|_| OronsayStatus::InvalidPuzzle)?;
+    Ok(Puzzle::new(grid).sudoku())
+}
+
+/// Solves `puzzle` and writes the first solution found to `solution`.
+///
+/// # Safety
+///
+/// `solver` must come from `oronsay_solver_new`; `puzzle` must point to 81
+/// readable bytes and `solution` to 81 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn oronsay_solve(
+    solver: *mut OronsaySolver,
+    puzzle: *const u8,
+    solution: *mut u8,
+) -> OronsayStatus {
+    let Some(solver) = solver.as_mut() else {
+        return OronsayStatus::NullPointer;
+    };
+    if solution.is_null() {
+        return OronsayStatus::NullPointer;
+    }
+    let sudoku = match read_puzzle(puzzle) {
+        Ok(sudoku) => sudoku,
+        Err(status) => return status,
+    };
+    match solver.solver.solve(&Puzzle::new(&sudoku.grid), &mut solver.state) {
+        Some(info) => {
+            ptr::copy_nonoverlapping(info.sudoku.grid.as_ptr(), solution, N_CELLS);
+            OronsayStatus::Ok
+        }
+        None => OronsayStatus::NoSolution,
+    }
+}
+
+/// Counts the solutions of `puzzle`, stopping at the solver's limit, and
+/// writes the count to `count`. A puzzle with no solution is `Ok` with a
+/// count of zero.
+///
+/// # Safety
+///
+/// `solver` must come from `oronsay_solver_new`; `puzzle` must point to 81
+/// readable bytes and `count` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn oronsay_count_solutions(
+    solver: *mut OronsaySolver,
+    puzzle: *const u8,
+    count: *mut usize,
+) -> OronsayStatus {
+    let Some(solver) = solver.as_mut() else {
+        return OronsayStatus::NullPointer;
+    };
+    if count.is_null() {
+        return OronsayStatus::NullPointer;
+    }
+    let sudoku = match read_puzzle(puzzle) {
+        Ok(sudoku) => sudoku,
+        Err(status) => return status,
+    };
+    let solution = solver.solver.solve(&Puzzle::new(&sudoku.grid), &mut solver.state);
+    *count = solution.map_or(0, |info| info.solutions);
+    OronsayStatus::Ok
+}
+
+/// Rates `puzzle` by the techniques needed to solve it by hand.
+///
+/// # Safety
+///
+/// `solver` must come from `oronsay_solver_new`; `puzzle` must point to 81
+/// readable bytes and `rating` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn oronsay_rate(
+    solver: *mut OronsaySolver,
+    puzzle: *const u8,
+    rating: *mut OronsayRating,
+) -> OronsayStatus {
+    let Some(solver) = solver.as_mut() else {
+        return OronsayStatus::NullPointer;
+    };
+    if rating.is_null() {
+        return OronsayStatus::NullPointer;
+    }
+    let sudoku = match read_puzzle(puzzle) {
+        Ok(sudoku) => sudoku,
+        Err(status) => return status,
+    };
+    match rate(&solver.rater, &mut solver.state, &sudoku) {
+        Some(found) => {
+            *rating = OronsayRating {
+                hardest: found.hardest.into(),
+                steps: found.steps,
+            };
+            OronsayStatus::Ok
+        }
+        None => match solver.rater.solve(&Puzzle::new(&sudoku.grid), &mut solver.state) {
+            Some(_) => OronsayStatus::NotUnique,
+            None => OronsayStatus::NoSolution,
+        },
+    }
+}
+
+/// Solves `count` puzzles of 81 bytes each, packed back to back, on
+/// `num_threads` threads (at least 1), each with its own search state.
+/// Solutions are written to `solutions` in the same layout; a puzzle with no
+/// solution gets 81 `.` bytes. The number solved is written to `solved`;
+/// with a `limit` above 1, a puzzle found to have more than one solution gets
+/// its first one but is not counted. Stops before solving anything at the
+/// first invalid puzzle, and returns `InvalidPuzzle` if `81 * count` bytes
+/// cannot be addressed.
+///
+/// # Safety
+///
+/// `solver` must come from `oronsay_solver_new`; `puzzles` must point to
+/// `81 * count` readable bytes, `solutions` to `81 * count` writable bytes and
+/// `solved` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn oronsay_solve_batch(
+    solver: *const OronsaySolver,
+    puzzles: *const u8,
+    count: usize,
+    num_threads: usize,
+    solutions: *mut u8,
+    solved: *mut usize,
+) -> OronsayStatus {
+    let Some(solver) = solver.as_ref() else {
+        return OronsayStatus::NullPointer;
+    };
+    if puzzles.is_null() || solutions.is_null() || solved.is_null() {
+        return OronsayStatus::NullPointer;
+    }
+    let Some(len) = count.checked_mul(N_CELLS).filter(|&len| len <= isize::MAX as usize) else {
+        return OronsayStatus::InvalidPuzzle;
+    };
+    let sudokus = match (0..count)
+        .map(|i| read_puzzle(puzzles.add(i * N_CELLS)))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(sudokus) => sudokus,
+        Err(status) => return status,
+    };
+
+    let report = solve_batch(&solver.solver, &sudokus, num_threads);
+    let out = slice::from_raw_parts_mut(solutions, len);
+    let mut unique = 0;
+    for (grid, solution) in out.chunks_exact_mut(N_CELLS).zip(&report.solutions) {
+        match solution {
+            Some(info) => {
+                grid.copy_from_slice(&info.sudoku.grid);
+                unique += usize::from(info.solutions == 1);
+            }
+            None => grid.fill(b'.'),
+        }
+    }
+    *solved = unique;
+    OronsayStatus::Ok
+}
+
+/// The name of `technique` as a static NUL-terminated string, e.g. "X-Wing".
+#[no_mangle]
+pub extern "C" fn oronsay_technique_name(technique: OronsayTechnique) -> *const c_char {
+    let name: &'static [u8] = match technique {
+        OronsayTechnique::FullHouse => b"Full House\0",
+        OronsayTechnique::HiddenSingle => b"Hidden Single\0",
+        OronsayTechnique::NakedSingle => b"Naked Single\0",
+        OronsayTechnique::Pointing => b"Pointing\0",
+        OronsayTechnique::Claiming => b"Claiming\0",
+        OronsayTechnique::NakedPair => b"Naked Pair\0",
+        OronsayTechnique::HiddenPair => b"Hidden Pair\0",
+        OronsayTechnique::NakedTriple => b"Naked Triple\0",
+        OronsayTechnique::XWing => b"X-Wing\0",
+        OronsayTechnique::Solver => b"Solver\0",
+    };
+    name.as_ptr().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASY: &[u8] = include_bytes!("../corpora/easy.txt");
+
+    fn puzzle(index: usize) -> &'static [u8] {
+        &EASY[index * (N_CELLS + 1)..][..N_CELLS]
+    }
+
+    /// Runs `f` with a fresh solver, freeing it afterwards.
+    fn with_solver(f: impl FnOnce(*mut OronsaySolver)) {
+        let solver = oronsay_solver_new(1);
+        f(solver);
+        unsafe { oronsay_solver_free(solver) };
+    }
+
+    #[test]
+    fn solve_checks_its_pointers() {
+        let mut solution = [0; N_CELLS];
+        let out = solution.as_mut_ptr();
+        with_solver(|solver| unsafe {
+            assert_eq!(oronsay_solve(ptr::null_mut(), puzzle(0).as_ptr(), out), OronsayStatus::NullPointer);
+            assert_eq!(oronsay_solve(solver, ptr::null(), out), OronsayStatus::NullPointer);
+            assert_eq!(oronsay_solve(solver, puzzle(0).as_ptr(), ptr::null_mut()), OronsayStatus::NullPointer);
+        });
+        assert_eq!(solution, [0; N_CELLS]);
+    }
+
+    #[test]
+    fn solve_rejects_invalid_puzzles_and_solves_valid_ones() {
+        let mut bad = puzzle(0).to_vec();
+        bad[40] = b'x';
+        let mut solution = [0; N_CELLS];
+        with_solver(|solver| unsafe {
+            assert_eq!(oronsay_solve(solver, bad.as_ptr(), solution.as_mut_ptr()), OronsayStatus::InvalidPuzzle);
+            assert_eq!(solution, [0; N_CELLS]);
+
+            assert_eq!(oronsay_solve(solver, puzzle(0).as_ptr(), solution.as_mut_ptr()), OronsayStatus::Ok);
+            assert!(Sudoku::new(solution).solves(&Puzzle::new(puzzle(0)).sudoku()));
+
+            let unsolvable = [b'1'; N_CELLS];
+            assert_eq!(oronsay_solve(solver, unsolvable.as_ptr(), solution.as_mut_ptr()), OronsayStatus::NoSolution);
+        });
+    }
+
+    #[test]
+    fn solve_batch_checks_its_pointers() {
+        let puzzles = puzzle(0);
+        let (mut solutions, mut solved) = ([0; N_CELLS], 0usize);
+        let (out, count) = (solutions.as_mut_ptr(), &mut solved as *mut usize);
+        with_solver(|solver| unsafe {
+            let batch = |solver, puzzles, out, count| oronsay_solve_batch(solver, puzzles, 1, 2, out, count);
+            assert_eq!(batch(ptr::null(), puzzles.as_ptr(), out, count), OronsayStatus::NullPointer);
+            assert_eq!(batch(solver, ptr::null(), out, count), OronsayStatus::NullPointer);
+            assert_eq!(batch(solver, puzzles.as_ptr(), ptr::null_mut(), count), OronsayStatus::NullPointer);
+            assert_eq!(batch(solver, puzzles.as_ptr(), out, ptr::null_mut()), OronsayStatus::NullPointer);
+        });
+        assert_eq!((solutions, solved), ([0; N_CELLS], 0));
+    }
+
+    #[test]
+    fn solve_batch_stops_at_an_invalid_puzzle() {
+        let mut puzzles = [puzzle(0), puzzle(1), puzzle(2)].concat();
+        let mut solutions = vec![0; puzzles.len()];
+        let mut solved = 0;
+        with_solver(|solver| unsafe {
+            let run = |puzzles: &[u8], solutions: &mut [u8], solved: &mut usize| {
+                oronsay_solve_batch(solver, puzzles.as_ptr(), 3, 2, solutions.as_mut_ptr(), solved)
+            };
+            assert_eq!(run(&puzzles, &mut solutions, &mut solved), OronsayStatus::Ok);
+            assert_eq!(solved, 3);
+            for (i, solution) in solutions.chunks_exact(N_CELLS).enumerate() {
+                assert!(Sudoku::new(solution.try_into().unwrap()).solves(&Puzzle::new(puzzle(i)).sudoku()));
+            }
+
+            puzzles[N_CELLS + 5] = b'?';
+            let (mut untouched, mut solved) = (vec![0; puzzles.len()], 0);
+            assert_eq!(run(&puzzles, &mut untouched, &mut solved), OronsayStatus::InvalidPuzzle);
+            assert_eq!((untouched, solved), (vec![0; puzzles.len()], 0));
+        });
+    }
+
+    #[test]
+    fn solve_batch_rejects_counts_past_the_address_space() {
+        let puzzles = puzzle(0);
+        let (mut solutions, mut solved) = ([0; N_CELLS], 0usize);
+        with_solver(|solver| unsafe {
+            for count in [usize::MAX, usize::MAX / N_CELLS + 1] {
+                let out = solutions.as_mut_ptr();
+                let status = oronsay_solve_batch(solver, puzzles.as_ptr(), count, 2, out, &mut solved);
+                assert_eq!(status, OronsayStatus::InvalidPuzzle);
+            }
+        });
+        assert_eq!((solutions, solved), ([0; N_CELLS], 0));
+    }
+
+    #[test]
+    fn solve_batch_counts_only_unique_solutions_past_a_limit_of_one() {
+        // Without its first row this puzzle has two solutions
+        let mut open = *b"53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+        open[..9].fill(b'.');
+        let puzzles = [puzzle(0), &open[..], puzzle(1)].concat();
+        for (limit, expected) in [(1, 3), (2, 2)] {
+            let (mut solutions, mut solved) = (vec![0; puzzles.len()], 0);
+            let solver = oronsay_solver_new(limit);
+            let status = unsafe {
+                let status = oronsay_solve_batch(solver, puzzles.as_ptr(), 3, 2, solutions.as_mut_ptr(), &mut solved);
+                oronsay_solver_free(solver);
+                status
+            };
+            assert_eq!(status, OronsayStatus::Ok);
+            assert_eq!(solved, expected, "limit {}", limit);
+            let first = Sudoku::new(solutions[N_CELLS..][..N_CELLS].try_into().unwrap());
+            assert!(first.solves(&Sudoku::new(open)));
+        }
+    }
+}
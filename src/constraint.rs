@@ -0,0 +1,251 @@
+use alloc::vec::Vec;
+
+use crate::{
+    candidates::{bit, col_of, row_of, Mask, ALL},
+    sudoku::N_CELLS,
+};
+
+/// A rule beyond rows, columns and regions, consulted by the solver each
+/// time it works out the candidates of a cell.
+///
+/// `values` holds the digits placed so far, `1..=9`, with `0` for an empty
+/// cell. A constraint must rule out every digit that would break it given
+/// those values, so that a grid filled one allowed digit at a time satisfies it.
+pub trait Constraint: Send + Sync {
+    /// Digits `cell` may still take.
+    fn allowed(&self, values: &[u8; N_CELLS], cell: usize) -> Mask;
+
+    /// Whether the filled cells of `values` keep the rule.
+    fn is_satisfied(&self, values: &[u8; N_CELLS]) -> bool {
+        let mut values = *values;
+        (0..N_CELLS).all(|cell| {
+            let value = core::mem::take(&mut values[cell]);
+            let ok = value == 0 || self.allowed(&values, cell) & bit(value) != 0;
+            values[cell] = value;
+            ok
+        })
+    }
+}
+
+/// Digits in `low..=high`, empty if the range is.
+fn range(low: i32, high: i32) -> Mask {
+    (low.max(1)..=high.min(9)).fold(0, |mask, value| mask | bit(value as u8))
+}
+
+/// Cells at the given (row, col) offsets from `cell` that are on the grid.
+fn offsets(cell: usize, deltas: &'static [(i32, i32)]) -> impl Iterator<Item = usize> {
+    let (row, col) = (row_of(cell) as i32, col_of(cell) as i32);
+    deltas.iter().filter_map(move |&(dr, dc)| {
+        let (row, col) = (row + dr, col + dc);
+        ((0..9).contains(&row) && (0..9).contains(&col)).then(|| (row * 9 + col) as usize)
+    })
+}
+
+const KNIGHT: [(i32, i32); 8] = [(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)];
+const KING: [(i32, i32); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+const ORTHOGONAL: [(i32, i32); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+/// Cells a chess knight's move apart hold different digits.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AntiKnight;
+
+impl Constraint for AntiKnight {
+    fn allowed(&self, values: &[u8; N_CELLS], cell: usize) -> Mask {
+        offsets(cell, &KNIGHT)
+            .filter(|&other| values[other] != 0)
+            .fold(ALL, |mask, other| mask & !bit(values[other]))
+    }
+}
+
+/// Cells a chess king's move apart, including diagonally, hold different digits.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AntiKing;
+
+impl Constraint for AntiKing {
+    fn allowed(&self, values: &[u8; N_CELLS], cell: usize) -> Mask {
+        offsets(cell, &KING)
+            .filter(|&other| values[other] != 0)
+            .fold(ALL, |mask, other| mask & !bit(values[other]))
+    }
+}
+
+/// Orthogonally adjacent cells do not hold consecutive digits.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NonConsecutive;
+
+impl Constraint for NonConsecutive {
+    fn allowed(&self, values: &[u8; N_CELLS], cell: usize) -> Mask {
+        offsets(cell, &ORTHOGONAL)
+            .filter(|&other| values[other] != 0)
+            .fold(ALL, |mask, other| {
+                let value = values[other] as i32;
+                mask & !range(value - 1, value - 1) & !range(value + 1, value + 1)
+            })
+    }
+}
+
+/// Digits strictly increase from the bulb, `cells[0]`, along the thermometer.
+#[derive(Clone, Debug)]
+pub struct Thermo {
+    pub cells: Vec<usize>,
+}
+
+impl Constraint for Thermo {
+    fn allowed(&self, values: &[u8; N_CELLS], cell: usize) -> Mask {
+        let Some(position) = self.cells.iter().position(|&other| other == cell) else {
+            return ALL;
+        };
+        let position = position as i32;
+        // Every step along the thermometer adds at least one
+        let mut low = position + 1;
+        let mut high = 9 - (self.cells.len() as i32 - 1 - position);
+        for (index, &other) in self.cells.iter().enumerate() {
+            let (index, value) = (index as i32, values[other] as i32);
+            if value == 0 || other == cell {
+                continue;
+            }
+            match index < position {
+                true => low = low.max(value + position - index),
+                false => high = high.min(value - (index - position)),
+            }
+        }
+        range(low, high)
+    }
+}
+
+/// The digits along an arrow add up to the digit in its circle.
+#[derive(Clone, Debug)]
+pub struct Arrow {
+    pub circle: usize,
+    pub cells: Vec<usize>,
+}
+
+impl Constraint for Arrow {
+    fn allowed(&self, values: &[u8; N_CELLS], cell: usize) -> Mask {
+        // Filled sum and number of empty cells along the arrow, leaving out `cell`
+        let (sum, empty) = self
+            .cells
+            .iter()
+            .filter(|&&other| other != cell)
+            .fold((0, 0), |(sum, empty), &other| match values[other] {
+                0 => (sum, empty + 1),
+                value => (sum + value as i32, empty),
+            });
+
+        if cell == self.circle {
+            return match empty {
+                0 => range(sum, sum),
+                _ => range(sum + empty, 9),
+            };
+        }
+        if !self.cells.contains(&cell) {
+            return ALL;
+        }
+        match values[self.circle] as i32 {
+            0 => range(1, 9 - sum - empty),
+            circle if empty == 0 => range(circle - sum, circle - sum),
+            circle => range(1, circle - sum - empty),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dot {
+    /// The two digits are consecutive.
+    White,
+    /// One digit is double the other.
+    Black,
+}
+
+/// A Kropki dot between two adjacent cells.
+#[derive(Clone, Copy, Debug)]
+pub struct Kropki {
+    pub cells: [usize; 2],
+    pub dot: Dot,
+}
+
+impl Constraint for Kropki {
+    fn allowed(&self, values: &[u8; N_CELLS], cell: usize) -> Mask {
+        let other = match self.cells {
+            [a, b] if a == cell => b,
+            [a, b] if b == cell => a,
+            _ => return ALL,
+        };
+        let value = values[other] as i32;
+        match (self.dot, value) {
+            (Dot::White, 0) => ALL,
+            (Dot::White, value) => range(value - 1, value - 1) | range(value + 1, value + 1),
+            // 5, 7 and 9 have no half or double among the digits
+            (Dot::Black, 0) => ALL & !(bit(5) | bit(7) | bit(9)),
+            (Dot::Black, value) => {
+                let half = match value % 2 {
+                    0 => range(value / 2, value / 2),
+                    _ => 0,
+                };
+                half | range(value * 2, value * 2)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    fn digits(mask: Mask) -> Vec<u8> {
+        (1..=9).filter(|&value| mask & bit(value) != 0).collect()
+    }
+
+    /// An empty grid but for `placed` (cell, digit) pairs.
+    fn values(placed: &[(usize, u8)]) -> [u8; N_CELLS] {
+        let mut values = [0; N_CELLS];
+        for &(cell, value) in placed {
+            values[cell] = value;
+        }
+        values
+    }
+
+    #[test]
+    fn chess_moves_rule_out_digits() {
+        let values = values(&[(40, 5), (22, 3)]);
+        assert_eq!(digits(AntiKnight.allowed(&values, 40 - 19)), [1, 2, 3, 4, 6, 7, 8, 9]);
+        assert_eq!(digits(AntiKnight.allowed(&values, 41)), [1, 2, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(digits(AntiKnight.allowed(&values, 44)), digits(ALL));
+        assert_eq!(digits(AntiKing.allowed(&values, 31)), [1, 2, 4, 6, 7, 8, 9]);
+        assert_eq!(digits(AntiKing.allowed(&values, 48)), [1, 2, 3, 4, 6, 7, 8, 9]);
+        assert_eq!(digits(NonConsecutive.allowed(&values, 41)), [1, 2, 3, 5, 7, 8, 9]);
+        assert_eq!(digits(NonConsecutive.allowed(&values, 50)), digits(ALL));
+        assert!(!AntiKing.is_satisfied(&self::values(&[(0, 1), (10, 1)])));
+        assert!(AntiKnight.is_satisfied(&self::values(&[(0, 1), (10, 1)])));
+    }
+
+    #[test]
+    fn thermo_and_arrow_bound_their_cells() {
+        let thermo = Thermo { cells: vec![0, 1, 2, 3] };
+        assert_eq!(digits(thermo.allowed(&values(&[]), 0)), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(digits(thermo.allowed(&values(&[(0, 2), (3, 7)]), 1)), [3, 4, 5]);
+        assert_eq!(digits(thermo.allowed(&values(&[]), 40)), digits(ALL));
+        assert!(!thermo.is_satisfied(&values(&[(1, 5), (2, 5)])));
+
+        let arrow = Arrow { circle: 0, cells: vec![1, 2] };
+        assert_eq!(digits(arrow.allowed(&values(&[]), 0)), [2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(digits(arrow.allowed(&values(&[(1, 3), (2, 4)]), 0)), [7]);
+        assert_eq!(digits(arrow.allowed(&values(&[(0, 6), (1, 2)]), 2)), [4]);
+        assert_eq!(digits(arrow.allowed(&values(&[(0, 6)]), 2)), [1, 2, 3, 4, 5]);
+        assert!(arrow.is_satisfied(&values(&[(0, 9), (1, 4), (2, 5)])));
+    }
+
+    #[test]
+    fn kropki_dots_pair_digits() {
+        let white = Kropki { cells: [0, 1], dot: Dot::White };
+        let black = Kropki { cells: [0, 1], dot: Dot::Black };
+        assert_eq!(digits(white.allowed(&values(&[(1, 5)]), 0)), [4, 6]);
+        assert_eq!(digits(white.allowed(&values(&[(1, 9)]), 0)), [8]);
+        assert_eq!(digits(black.allowed(&values(&[]), 0)), [1, 2, 3, 4, 6, 8]);
+        assert_eq!(digits(black.allowed(&values(&[(0, 4)]), 1)), [2, 8]);
+        assert_eq!(digits(black.allowed(&values(&[(0, 3)]), 1)), [6]);
+        assert_eq!(digits(black.allowed(&values(&[(0, 3)]), 2)), digits(ALL));
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use crate::{
+    alphabet::Alphabet,
+    batch::map_chunks,
+    error::{Error, Result},
+    reader::is_header,
+    sudoku::{Puzzle, Sudoku, N_CELLS},
+};
+
+/// Lines read and canonicalised at a time.
+const BLOCK_LINES: usize = 1 << 16;
+
+/// Puzzles that are all equivalent under `Sudoku::canonical`.
+pub struct DuplicateClass {
+    pub canonical: Sudoku,
+    /// Position of the first member, which is the one kept, among all puzzles pushed.
+    pub first: usize,
+    pub count: usize,
+}
+
+/// Streaming symmetry-aware deduplication.
+pub struct Dedup {
+    classes: Vec<DuplicateClass>,
+    index: HashMap<[u8; N_CELLS], usize>,
+    num_threads: usize,
+    alphabet: Alphabet,
+    seen: usize,
+}
+
+impl Dedup {
+    pub fn new(num_threads: usize) -> Self {
+        Self {
+            classes: Vec::new(),
+            index: HashMap::new(),
+            num_threads: num_threads.max(1),
+            alphabet: Alphabet::default(),
+            seen: 0,
+        }
+    }
+
+    /// Which bytes `run` accepts as empty cells; others but `1`-`9` are an error.
+    pub fn alphabet(mut self, alphabet: Alphabet) -> Self {
+        self.alphabet = alphabet;
+        self
+    }
+
+    /// Records `puzzles` and returns whether each one is the first of its class.
+    pub fn push(&mut self, puzzles: &[Sudoku]) -> Vec<bool> {
+        let canonical = map_chunks(
+            puzzles,
+            self.num_threads,
+            || (),
+            |chunk, _| chunk.iter().map(Sudoku::canonical).collect::<Vec<_>>(),
+        );
+
+        let first = self.seen;
+        self.seen += puzzles.len();
+        canonical
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(offset, canonical)| match self.index.get(&canonical.grid) {
+                Some(&class) => {
+                    self.classes[class].count += 1;
+                    false
+                }
+                None => {
+                    self.index.insert(canonical.grid, self.classes.len());
+                    self.classes.push(DuplicateClass {
+                        canonical,
+                        first: first + offset,
+                        count: 1,
+                    });
+                    true
+                }
+            })
+            .collect()
+    }
+
+    pub fn classes(&self) -> &[DuplicateClass] {
+        &self.classes
+    }
+
+    /// Number of puzzles dropped so far.
+    pub fn duplicates(&self) -> usize {
+        self.classes.iter().map(|class| class.count - 1).sum()
+    }
+
+    /// Copies the first puzzle of each class from `input` to `output`. A header
+    /// line, as the pipeline's reader detects it, is copied as is.
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> Result<()> {
+        let mut lines = input.split(b'\n').enumerate().peekable();
+        let mut first = None;
+        if let Some((index, line)) = lines.next() {
+            let line = line?;
+            match lines.peek() {
+                Some((_, Ok(second))) if is_header(&line, second) => {
+                    output.write_all(&line)?;
+                    output.write_all(b"\n")?;
+                }
+                _ => first = Some((index, Ok(line))),
+            }
+        }
+        let mut lines = first.into_iter().chain(lines);
+
+        let mut block = Vec::with_capacity(BLOCK_LINES);
+        loop {
+            block.clear();
+            for (index, line) in lines.by_ref() {
+                let line = line?;
+                if line.strip_suffix(b"\r").unwrap_or(&line).is_empty() {
+                    continue;
+                }
+                block.push((index + 1, line));
+                if block.len() == BLOCK_LINES {
+                    break;
+                }
+            }
+            if block.is_empty() {
+                output.flush()?;
+                return Ok(());
+            }
+
+            let puzzles = block
+                .iter()
+                .map(|(line_number, line)| match line.get(..N_CELLS) {
+                    Some(grid) => {
+                        self.alphabet.validate(*line_number, grid)?;
+                        Ok(Puzzle::new(grid).sudoku())
+                    }
+                    None => Err(Error::Format(format!(
+                        "line {} is {} bytes long, expected at least {}",
+                        line_number,
+                        line.len(),
+                        N_CELLS
+                    ))),
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let keep = self.push(&puzzles);
+            for ((_, line), keep) in block.iter().zip(keep) {
+                if keep {
+                    output.write_all(line)?;
+                    output.write_all(b"\n")?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::transform::Transform;
+
+    use super::*;
+
+    const PUZZLE: &[u8; N_CELLS] =
+        b"53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+    const OTHER: &[u8] = b".5....2....9..2.81.8..3.9...3.....5..7.6.1..851.8.......4.1...9.....8.......694..";
+
+    fn text(lines: &[&[u8]]) -> String {
+        lines.iter().map(|line| String::from_utf8_lossy(line) + "\n").collect()
+    }
+
+    fn run(dedup: &mut Dedup, input: &[u8]) -> Result<String> {
+        let mut output = Vec::new();
+        dedup.run(input, &mut output)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn keeps_the_first_of_each_class() {
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let isomorph = Transform::random(&mut rng).apply(&Sudoku::new(*PUZZLE));
+        let lines = [&PUZZLE[..], OTHER, &isomorph.grid, &PUZZLE[..], OTHER];
+        let input = text(&lines);
+
+        for threads in [1, 3] {
+            let mut dedup = Dedup::new(threads);
+            let output = run(&mut dedup, format!("puzzles\n{}", input).as_bytes()).unwrap();
+            assert_eq!(output, format!("puzzles\n{}", text(&lines[..2])));
+            assert_eq!(dedup.duplicates(), 3);
+            let counts = dedup.classes().iter().map(|class| (class.first, class.count)).collect::<Vec<_>>();
+            assert_eq!(counts, [(0, 3), (1, 2)]);
+        }
+
+        // Without a header, and with Windows line endings kept on the output
+        let crlf = input.replace('\n', "\r\n");
+        let output = run(&mut Dedup::new(2), crlf.as_bytes()).unwrap();
+        assert_eq!(output.lines().count(), 2);
+        assert!(output.starts_with(&format!("{}\r\n", String::from_utf8_lossy(PUZZLE))));
+    }
+
+    #[test]
+    fn checks_every_puzzle_against_the_alphabet() {
+        let mut bad = *PUZZLE;
+        bad[3] = b'x';
+        let input = [&PUZZLE[..], b"\n", OTHER, b"\n", &bad, b"\n"].concat();
+        match run(&mut Dedup::new(1), &input) {
+            Err(Error::InvalidCharacter { line, column, byte }) => assert_eq!((line, column, byte), (3, 4, b'x')),
+            other => panic!("expected an invalid character, got {:?}", other),
+        }
+
+        let dashes = String::from_utf8_lossy(&bad).replace(['.', 'x'], "-");
+        let mut dedup = Dedup::new(1).alphabet(Alphabet::new("-").unwrap());
+        assert_eq!(run(&mut dedup, format!("{0}\n{0}\n", dashes).as_bytes()).unwrap(), format!("{}\n", dashes));
+        // Permissively the `x` is an empty cell, so the last puzzle repeats the first
+        let mut dedup = Dedup::new(1).alphabet(Alphabet::permissive());
+        assert_eq!(run(&mut dedup, &input).unwrap().lines().count(), 2);
+    }
+}
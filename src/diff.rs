@@ -0,0 +1,217 @@
+use std::fmt;
+
+use crate::{
+    batch::for_each_chunk,
+    solver::{Budget, Outcome, Solver},
+    sudoku::{Puzzle, Sudoku},
+};
+
+/// What one solver made of one puzzle.
+#[derive(Clone, Copy)]
+pub enum Verdict {
+    Solved { sudoku: Sudoku, solutions: usize },
+    NoSolution,
+    GaveUp,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Solved { sudoku, solutions } => {
+                write!(f, "{} solution(s), {}", solutions, sudoku)
+            }
+            Verdict::NoSolution => write!(f, "no solution"),
+            Verdict::GaveUp => write!(f, "gave up"),
+        }
+    }
+}
+
+/// A puzzle on which the solvers did not agree.
+pub struct Disagreement {
+    /// Index of the puzzle in the input.
+    pub index: usize,
+    pub puzzle: Sudoku,
+    /// One verdict per solver, in the order they were added.
+    pub verdicts: Vec<Verdict>,
+    pub reason: &'static str,
+}
+
+pub struct DiffReport {
+    pub solvers: Vec<String>,
+    pub puzzles: usize,
+    /// Puzzles skipped because at least one solver gave up.
+    pub gave_up: usize,
+    pub disagreements: Vec<Disagreement>,
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for disagreement in &self.disagreements {
+            writeln!(
+                f,
+                "puzzle {}: {}",
+                disagreement.index + 1,
+                disagreement.puzzle
+            )?;
+            writeln!(f, "  {}", disagreement.reason)?;
+            for (name, verdict) in self.solvers.iter().zip(&disagreement.verdicts) {
+                writeln!(f, "  {:>12}: {}", name, verdict)?;
+            }
+        }
+        writeln!(
+            f,
+            "{} puzzles, {} disagreements, {} skipped after a solver gave up",
+            self.puzzles,
+            self.disagreements.len(),
+            self.gave_up
+        )
+    }
+}
+
+/// Object-safe wrapper so solvers with different `State` types can be compared.
+trait Candidate: Sync {
+    fn verdicts(&self, puzzles: &[Sudoku], num_threads: usize, budget: &Budget) -> Vec<Verdict>;
+}
+
+impl<S: Solver> Candidate for S {
+    fn verdicts(&self, puzzles: &[Sudoku], num_threads: usize, budget: &Budget) -> Vec<Verdict> {
+        for_each_chunk(self, puzzles, num_threads, |chunk, state| {
+            chunk
+                .iter()
+                .map(|sudoku| match self.solve_within(&Puzzle::new(&sudoku.grid), state, budget) {
+                    Outcome::Solved(solution) => Verdict::Solved {
+                        sudoku: solution.sudoku,
+                        solutions: solution.solutions,
+                    },
+                    Outcome::NoSolution => Verdict::NoSolution,
+                    Outcome::GaveUp { .. } => Verdict::GaveUp,
+                })
+                .collect::<Vec<_>>()
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+/// Runs several solvers over the same puzzles and reports where they disagree.
+///
+/// Solvers should be configured to look for at least two solutions so that
+/// solution counts can be compared. Grids are only compared for puzzles every
+/// solver found unique, but each reported grid is checked against the givens.
+pub struct DiffHarness {
+    names: Vec<String>,
+    solvers: Vec<Box<dyn Candidate>>,
+    num_threads: usize,
+    budget: Budget,
+}
+
+impl Default for DiffHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiffHarness {
+    pub fn new() -> Self {
+        Self {
+            names: Vec::new(),
+            solvers: Vec::new(),
+            num_threads: 1,
+            budget: Budget::unlimited(),
+        }
+    }
+
+    pub fn solver<S: Solver>(mut self, name: impl Into<String>, solver: S) -> Self {
+        self.names.push(name.into());
+        self.solvers.push(Box::new(solver));
+        self
+    }
+
+    pub fn threads(mut self, num_threads: usize) -> Self {
+        self.num_threads = num_threads.max(1);
+        self
+    }
+
+    /// Per-puzzle limits; puzzles where any solver gives up are skipped.
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub fn run(&self, puzzles: &[Sudoku]) -> DiffReport {
+        let results = self
+            .solvers
+            .iter()
+            .map(|solver| solver.verdicts(puzzles, self.num_threads, &self.budget))
+            .collect::<Vec<_>>();
+
+        let mut report = DiffReport {
+            solvers: self.names.clone(),
+            puzzles: puzzles.len(),
+            gave_up: 0,
+            disagreements: Vec::new(),
+        };
+        for (index, puzzle) in puzzles.iter().enumerate() {
+            let verdicts = results.iter().map(|v| v[index]).collect::<Vec<_>>();
+            if verdicts.iter().any(|v| matches!(v, Verdict::GaveUp)) {
+                report.gave_up += 1;
+                continue;
+            }
+            if let Some(reason) = Self::compare(puzzle, &verdicts) {
+                report.disagreements.push(Disagreement {
+                    index,
+                    puzzle: *puzzle,
+                    verdicts,
+                    reason,
+                });
+            }
+        }
+        report
+    }
+
+    fn compare(puzzle: &Sudoku, verdicts: &[Verdict]) -> Option<&'static str> {
+        let mut counts = verdicts.iter().map(|verdict| match verdict {
+            Verdict::Solved { solutions, .. } => *solutions,
+            _ => 0,
+        });
+        let first_count = counts.next()?;
+        if counts.any(|count| count != first_count) {
+            return Some("solution counts differ");
+        }
+
+        let grids = verdicts
+            .iter()
+            .filter_map(|verdict| match verdict {
+                Verdict::Solved { sudoku, .. } => Some(sudoku),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if grids.iter().any(|grid| !grid.solves(puzzle)) {
+            return Some("invalid solution grid");
+        }
+        if first_count == 1 && grids.windows(2).any(|pair| pair[0].grid != pair[1].grid) {
+            return Some("solution grids differ");
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bench::Corpus, solver::SolverBasic};
+
+    #[test]
+    fn mcv_and_first_empty_cell_agree_on_the_builtin_corpora() {
+        let harness = DiffHarness::new()
+            .solver("mcv", SolverBasic::new(2, true))
+            .solver("first", SolverBasic::new(2, false))
+            .threads(2);
+        for name in Corpus::builtin_names() {
+            let corpus = Corpus::builtin(name).unwrap().unwrap();
+            let report = harness.run(&corpus.puzzles);
+            assert!(report.disagreements.is_empty(), "{}: {}", name, report);
+        }
+    }
+}
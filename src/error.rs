@@ -0,0 +1,61 @@
+use alloc::string::String;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Reading the input or writing the output failed.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// The input is not a puzzle file we understand.
+    Format(String),
+    /// The solver found no solution for the puzzle on `line` (1-based).
+    Unsolvable { line: usize, puzzle: String },
+    /// A puzzle holds a byte that is neither a digit nor one of the empty
+    /// characters, at `line` and `column` (both 1-based).
+    InvalidCharacter { line: usize, column: usize, byte: u8 },
+    /// A pipeline stage stopped unexpectedly or was misconfigured.
+    Pipeline(String),
+    /// The run was stopped through a `CancelToken`.
+    Cancelled,
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Format(reason) => write!(f, "invalid input: {}", reason),
+            Error::Unsolvable { line, puzzle } => {
+                write!(f, "no solution for puzzle on line {}: {}", line, puzzle)
+            }
+            Error::InvalidCharacter { line, column, byte } => write!(
+                f,
+                "unexpected character {:?} on line {}, column {}",
+                *byte as char, line, column
+            ),
+            Error::Pipeline(reason) => write!(f, "pipeline error: {}", reason),
+            Error::Cancelled => write!(f, "run was cancelled"),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,560 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{self, Write};
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::{io, path::Path};
+
+use crate::{
+    batch::parse_puzzles,
+    candidates::{bit, value_of, CandidateGrid},
+    error::{Error, Result},
+    sudoku::{Sudoku, N_CELLS},
+};
+
+/// Puzzle file formats that `convert` reads and writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// One 81 character puzzle per line, `.` for empty, as the pipeline reads.
+    Line,
+    /// SadMan Sudoku single puzzle: `#` metadata lines, then nine rows of nine.
+    Sdk,
+    /// SadMan Sudoku collection: one 81 character puzzle per line, `0` for empty.
+    Sdm,
+    /// Simple Sudoku: nine rows with `|` between boxes and `-` lines between bands.
+    Ss,
+    /// Nine rows of nine cells per puzzle, spaces and box lines ignored.
+    Grid,
+    /// HoDoKu pencil mark grid, or its library lines of givens and deleted
+    /// candidates. A pencil mark cell down to one candidate reads as filled.
+    Hodoku,
+}
+
+/// A puzzle read from a file, with the pencil marks if the format has them.
+#[derive(Clone)]
+pub struct Record {
+    pub sudoku: Sudoku,
+    pub candidates: Option<CandidateGrid>,
+}
+
+impl From<Sudoku> for Record {
+    fn from(sudoku: Sudoku) -> Self {
+        Self {
+            sudoku,
+            candidates: None,
+        }
+    }
+}
+
+/// Characters that stand for an empty cell in grid layouts.
+fn is_empty(ch: char) -> bool {
+    matches!(ch, '.' | '0' | '_' | '*' | 'x' | 'X')
+}
+
+/// Whether a line only separates bands of a grid.
+fn is_border(line: &str) -> bool {
+    line.contains(['-', '=', '─'])
+}
+
+/// The cells in nine-by-nine layouts, nine rows per puzzle. Lines starting
+/// with `#` are comments.
+fn parse_grids(text: &str) -> Result<Vec<Record>> {
+    let mut records = Vec::new();
+    let mut grid = Vec::with_capacity(N_CELLS);
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') || is_border(line) {
+            continue;
+        }
+        let start = grid.len();
+        for ch in line.chars().filter(|&ch| !ch.is_whitespace() && !matches!(ch, '|' | '│')) {
+            match ch {
+                '1'..='9' => grid.push(ch as u8),
+                ch if is_empty(ch) => grid.push(b'.'),
+                _ => {
+                    return Err(Error::Format(format!(
+                        "line {}: unexpected character '{}' in grid",
+                        index + 1,
+                        ch
+                    )))
+                }
+            }
+        }
+        if grid.len() - start != 9 {
+            return Err(Error::Format(format!(
+                "line {} has {} cells, expected 9",
+                index + 1,
+                grid.len() - start
+            )));
+        }
+        if grid.len() == N_CELLS {
+            let cells = core::mem::take(&mut grid).try_into().expect("grid has 81 cells");
+            records.push(Sudoku::new(cells).into());
+        }
+    }
+    if !grid.is_empty() {
+        return Err(Error::Format(format!("last grid has only {} rows", grid.len() / 9)));
+    }
+    Ok(records)
+}
+
+/// SadMan files put the grid after `[Puzzle]` when they have sections.
+fn parse_sdk(text: &str) -> Result<Vec<Record>> {
+    let grid = match text.split_once("[Puzzle]") {
+        Some((_, rest)) => rest.split("\n[").next().unwrap_or_default(),
+        None => text,
+    };
+    let records = parse_grids(grid)?;
+    match records.len() {
+        1 => Ok(records),
+        found => Err(Error::Format(format!("an sdk file holds one puzzle, found {}", found))),
+    }
+}
+
+/// Pencil mark grids: a cell is its candidates written together, so a
+/// single digit is a filled cell.
+fn parse_pencil_marks(text: &str) -> Result<Vec<Record>> {
+    let mut records = Vec::new();
+    let mut cells = Vec::with_capacity(N_CELLS);
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') || is_border(line) {
+            continue;
+        }
+        let start = cells.len();
+        for token in line.split(|ch: char| ch.is_whitespace() || ch == '|').filter(|token| !token.is_empty()) {
+            let mut mask = 0;
+            for ch in token.chars() {
+                match ch.to_digit(10) {
+                    Some(digit @ 1..=9) => mask |= bit(digit as u8),
+                    _ => {
+                        return Err(Error::Format(format!(
+                            "line {}: '{}' is not a list of candidates",
+                            index + 1,
+                            token
+                        )))
+                    }
+                }
+            }
+            cells.push(mask);
+        }
+        if cells.len() - start != 9 {
+            return Err(Error::Format(format!(
+                "line {} has {} cells, expected 9",
+                index + 1,
+                cells.len() - start
+            )));
+        }
+        if cells.len() == N_CELLS {
+            let mut candidates = CandidateGrid::new();
+            for (cell, mask) in cells.drain(..).enumerate() {
+                for value in (1..=9).filter(|&value| mask & bit(value) == 0) {
+                    candidates.eliminate(cell, value);
+                }
+            }
+            records.push(Record {
+                sudoku: candidates.to_sudoku(),
+                candidates: Some(candidates),
+            });
+        }
+    }
+    if !cells.is_empty() {
+        return Err(Error::Format(format!("last grid has only {} rows", cells.len() / 9)));
+    }
+    Ok(records)
+}
+
+/// HoDoKu library lines, `:<technique>:<digits>:<grid>:<deleted>:...`. The
+/// grid marks placed digits that are not givens with `+`, and deleted
+/// candidates are digit, row, column triples such as `513`.
+fn parse_library(text: &str) -> Result<Vec<Record>> {
+    let mut records = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = line.split(':').collect::<Vec<_>>();
+        let error = |reason: &str| Error::Format(format!("line {}: {}", index + 1, reason));
+        let (Some(grid), deleted) = (fields.get(3), fields.get(4)) else {
+            return Err(error("expected ':<technique>:<digits>:<grid>:' fields"));
+        };
+
+        let values = grid.chars().filter(|&ch| ch != '+').collect::<Vec<_>>();
+        if values.len() != N_CELLS {
+            return Err(error(&format!("grid has {} cells, expected {}", values.len(), N_CELLS)));
+        }
+        let mut cells = [b'.'; N_CELLS];
+        for (cell, &ch) in cells.iter_mut().zip(&values) {
+            match ch {
+                '1'..='9' => *cell = ch as u8,
+                ch if is_empty(ch) => {}
+                _ => return Err(error(&format!("unexpected character '{}' in grid", ch))),
+            }
+        }
+        let sudoku = Sudoku::new(cells);
+
+        let mut candidates = CandidateGrid::from_sudoku(&sudoku);
+        for triple in deleted.unwrap_or(&"").split_whitespace() {
+            match triple.as_bytes() {
+                &[digit, row, col] if [digit, row, col].iter().all(|byte| (b'1'..=b'9').contains(byte)) => {
+                    candidates.eliminate((row - b'1') as usize * 9 + (col - b'1') as usize, digit - b'0');
+                }
+                _ => return Err(error(&format!("'{}' is not a deleted candidate", triple))),
+            }
+        }
+        records.push(Record {
+            sudoku,
+            candidates: (!deleted.unwrap_or(&"").trim().is_empty()).then_some(candidates),
+        });
+    }
+    Ok(records)
+}
+
+fn write_rows(out: &mut impl Write, sudoku: &Sudoku, boxes: bool) -> fmt::Result {
+    for row in 0..9 {
+        if boxes && (row == 3 || row == 6) {
+            writeln!(out, "-----------")?;
+        }
+        let mut line = String::new();
+        for col in 0..9 {
+            if boxes && (col == 3 || col == 6) {
+                line.push('|');
+            }
+            line.push(match value_of(sudoku.grid[row * 9 + col]) {
+                Some(value) => (b'0' + value) as char,
+                None => '.',
+            });
+        }
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// HoDoKu's pencil mark grid, each column as wide as its longest cell.
+fn write_pencil_marks(out: &mut impl Write, candidates: &CandidateGrid) -> fmt::Result {
+    let text = |cell: usize| candidates.candidates(cell).map(|value| (b'0' + value) as char).collect::<String>();
+    let widths = (0..9)
+        .map(|col| (0..9).map(|row| text(row * 9 + col).len()).max().unwrap_or(1))
+        .collect::<Vec<_>>();
+    let border = |left: char, middle: char, right: char| {
+        let mut line = String::new();
+        for stack in 0..3 {
+            line.push(if stack == 0 { left } else { middle });
+            let width = widths[stack * 3..stack * 3 + 3].iter().sum::<usize>() + 6;
+            line.push_str(&"-".repeat(width));
+        }
+        line.push(right);
+        line
+    };
+
+    writeln!(out, "{}", border('.', '.', '.'))?;
+    for row in 0..9 {
+        if row == 3 || row == 6 {
+            writeln!(out, "{}", border(':', '+', ':'))?;
+        }
+        let mut line = String::new();
+        for (col, &width) in widths.iter().enumerate() {
+            line.push_str(match col % 3 {
+                0 => "| ",
+                _ => "  ",
+            });
+            line.push_str(&format!("{:<width$}", text(row * 9 + col)));
+            if col % 3 == 2 {
+                line.push(' ');
+            }
+        }
+        line.push('|');
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(out, "{}", border('\'', '\'', '\''))?;
+    Ok(())
+}
+
+impl Format {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Line => "line",
+            Format::Sdk => "sdk",
+            Format::Sdm => "sdm",
+            Format::Ss => "ss",
+            Format::Grid => "grid",
+            Format::Hodoku => "hodoku",
+        }
+    }
+
+    /// The format a file extension names: `.sdk`, `.sdm` or `.ss`.
+    #[cfg(feature = "std")]
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "sdk" => Some(Format::Sdk),
+            "sdm" => Some(Format::Sdm),
+            "ss" => Some(Format::Ss),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the first lines of a file.
+    pub fn detect(text: &str) -> Self {
+        let lines = text
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let content = lines
+            .iter()
+            .copied()
+            .filter(|line| !line.starts_with('#'))
+            .collect::<Vec<_>>();
+        let Some(&first) = content.first() else {
+            return Format::Line;
+        };
+
+        if text.contains("[Puzzle]") {
+            Format::Sdk
+        } else if first.starts_with(':') || (first.starts_with('.') && is_border(first)) {
+            Format::Hodoku
+        } else if content.iter().any(|line| line.contains('|')) && !content.iter().any(|line| line.contains('│')) {
+            Format::Ss
+        } else if first.len() >= N_CELLS {
+            match first.bytes().any(|byte| byte == b'0') {
+                true => Format::Sdm,
+                false => Format::Line,
+            }
+        } else if lines.first().is_some_and(|line| line.starts_with('#')) && content.len() == 9 {
+            Format::Sdk
+        } else {
+            Format::Grid
+        }
+    }
+
+    /// The format of `path` from its extension, or else its contents.
+    #[cfg(feature = "std")]
+    pub fn detect_file(path: &Path, text: &str) -> Self {
+        Self::from_extension(path).unwrap_or_else(|| Self::detect(text))
+    }
+
+    pub fn parse(&self, text: &str) -> Result<Vec<Record>> {
+        match self {
+            Format::Line | Format::Sdm => Ok(parse_puzzles(text.as_bytes())?
+                .into_iter()
+                .map(|sudoku| sudoku.clean().into())
+                .collect()),
+            Format::Sdk => parse_sdk(text),
+            Format::Ss | Format::Grid => parse_grids(text),
+            Format::Hodoku => match text.trim_start().starts_with(':') {
+                true => parse_library(text),
+                false => parse_pencil_marks(text),
+            },
+        }
+    }
+
+    /// The text of `records` one after another. Only HoDoKu keeps pencil
+    /// marks; records without them get every candidate the givens allow.
+    pub fn render(&self, records: &[Record]) -> Result<String> {
+        if *self == Format::Sdk && records.len() != 1 {
+            return Err(Error::Format(format!(
+                "an sdk file holds one puzzle, got {}",
+                records.len()
+            )));
+        }
+        let mut out = String::new();
+        self.render_into(records, &mut out)
+            .expect("writing to a String cannot fail");
+        Ok(out)
+    }
+
+    fn render_into(&self, records: &[Record], out: &mut String) -> fmt::Result {
+        for (index, record) in records.iter().enumerate() {
+            let sudoku = record.sudoku.clean();
+            match self {
+                Format::Line => writeln!(out, "{}", sudoku)?,
+                Format::Sdm => writeln!(out, "{}", sudoku.to_string().replace('.', "0"))?,
+                Format::Sdk => write_rows(out, &sudoku, false)?,
+                Format::Ss | Format::Grid => {
+                    if index > 0 {
+                        writeln!(out)?;
+                    }
+                    write_rows(out, &sudoku, *self == Format::Ss)?;
+                }
+                Format::Hodoku => {
+                    if index > 0 {
+                        writeln!(out)?;
+                    }
+                    let candidates = record
+                        .candidates
+                        .unwrap_or_else(|| CandidateGrid::from_sudoku(&sudoku));
+                    write_pencil_marks(out, &candidates)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes `records` as `render` lays them out.
+    #[cfg(feature = "std")]
+    pub fn write(&self, records: &[Record], mut out: impl io::Write) -> Result<()> {
+        out.write_all(self.render(records)?.as_bytes())?;
+        Ok(out.flush()?)
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        [
+            Format::Line,
+            Format::Sdk,
+            Format::Sdm,
+            Format::Ss,
+            Format::Grid,
+            Format::Hodoku,
+        ]
+        .into_iter()
+        .find(|format| format.name().eq_ignore_ascii_case(name.trim()))
+        .ok_or_else(|| Error::Format(format!("unknown puzzle format '{}'", name)))
+    }
+}
+
+/// Parses a single puzzle in any of the `Format`s, detected from the text.
+impl FromStr for Sudoku {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut records = Format::detect(text).parse(text)?;
+        match records.len() {
+            1 => Ok(records.remove(0).sudoku),
+            found => Err(Error::Format(format!("expected one puzzle, found {}", found))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &[u8; N_CELLS] =
+        b"53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+    const SOLUTION: &[u8; N_CELLS] =
+        b"534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+    fn error<T>(result: Result<T>) -> String {
+        match result {
+            Err(Error::Format(message)) => message,
+            Err(err) => panic!("expected a format error, got {}", err),
+            Ok(_) => panic!("expected a format error"),
+        }
+    }
+
+    fn sudokus(records: &[Record]) -> Vec<Sudoku> {
+        records.iter().map(|record| record.sudoku).collect()
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let records = [Sudoku::new(*PUZZLE).into(), Sudoku::new(*SOLUTION).into()];
+        for format in [Format::Line, Format::Sdm, Format::Ss, Format::Grid, Format::Hodoku] {
+            let text = format.render(&records).unwrap();
+            assert_eq!(Format::detect(&text), format, "{}", text);
+            let parsed = format.parse(&text).unwrap();
+            match format {
+                // Pencil marks fill in cells down to one candidate
+                Format::Hodoku => {
+                    for (parsed, record) in parsed.iter().zip(&records) {
+                        assert_eq!(parsed.candidates, Some(CandidateGrid::from_sudoku(&record.sudoku)));
+                    }
+                }
+                _ => assert_eq!(sudokus(&parsed), sudokus(&records), "{}", format),
+            }
+        }
+        let text = Format::Sdk.render(&records[..1]).unwrap();
+        assert_eq!(sudokus(&Format::Sdk.parse(&text).unwrap()), sudokus(&records[..1]));
+        assert!(error(Format::Sdk.render(&records)).contains("holds one puzzle, got 2"));
+
+        assert!(Format::Sdm.render(&records).unwrap().starts_with("530070000600195000"));
+        assert_eq!(Format::Ss.render(&records[..1]).unwrap().lines().nth(3), Some("-----------"));
+    }
+
+    #[test]
+    fn hodoku_keeps_pencil_marks() {
+        let mut candidates = CandidateGrid::from_sudoku(&Sudoku::new(*PUZZLE));
+        candidates.eliminate(2, 1);
+        candidates.eliminate(78, 1);
+        let record = Record {
+            sudoku: candidates.to_sudoku(),
+            candidates: Some(candidates),
+        };
+        let text = Format::Hodoku.render(&[record]).unwrap();
+        let parsed = Format::Hodoku.parse(&text).unwrap();
+        assert_eq!(parsed[0].candidates, Some(candidates));
+        assert_eq!(parsed[0].sudoku, candidates.to_sudoku());
+
+        // Library lines: placed digits marked `+`, then deleted candidates as digit, row, column
+        let line = format!(":0000:x:+{}:113 991:", Sudoku::new(*PUZZLE));
+        let parsed = Format::Hodoku.parse(&line).unwrap();
+        assert_eq!(parsed[0].sudoku, Sudoku::new(*PUZZLE));
+        let deleted = parsed[0].candidates.unwrap();
+        assert!(!deleted.contains(2, 1) && !deleted.contains(72, 9) && deleted.contains(2, 2));
+        assert!(error(Format::Hodoku.parse(":0000:x:123:")).contains("grid has 3 cells"));
+        assert!(error(Format::Hodoku.parse(&format!(":0000:x:{}:1a3:", Sudoku::new(*PUZZLE)))).contains("'1a3'"));
+    }
+
+    #[test]
+    fn detection_tells_apart_similar_layouts() {
+        let line = Sudoku::new(*PUZZLE).to_string();
+        assert_eq!(Format::detect(&line), Format::Line);
+        assert_eq!(Format::detect(&format!("# comment\n{}", line)), Format::Line);
+        assert_eq!(Format::detect(&line.replace('.', "0")), Format::Sdm);
+        assert_eq!(Format::detect(""), Format::Line);
+
+        // Nine rows are SadMan with a `#` header, a plain grid without
+        let rows = Format::Grid.render(&[Sudoku::new(*PUZZLE).into()]).unwrap();
+        assert_eq!(Format::detect(&rows), Format::Grid);
+        assert_eq!(Format::detect(&format!("#Aauthor\n{}", rows)), Format::Sdk);
+        assert_eq!(Format::detect(&format!("[Puzzle]\n{}", rows)), Format::Sdk);
+
+        // `|` makes Simple Sudoku, unless the box lines are those of `pretty`
+        assert_eq!(Format::detect("53.|.7.|...\n"), Format::Ss);
+        assert_eq!(Format::detect(&Sudoku::new(*PUZZLE).pretty()), Format::Grid);
+        assert_eq!(Format::detect(".---------.\n"), Format::Hodoku);
+        assert_eq!(Format::detect(&format!(":0000:x:{}::", line)), Format::Hodoku);
+        assert_eq!(
+            sudokus(&Format::Grid.parse(&Sudoku::new(*PUZZLE).pretty()).unwrap()),
+            [Sudoku::new(*PUZZLE)]
+        );
+    }
+
+    #[test]
+    fn sudoku_from_str_reads_any_single_puzzle() {
+        let sudoku = Sudoku::new(*PUZZLE);
+        for format in [Format::Line, Format::Sdm, Format::Sdk, Format::Ss, Format::Grid] {
+            let text = format.render(&[sudoku.into()]).unwrap();
+            assert_eq!(text.parse::<Sudoku>().unwrap(), sudoku, "{}", format);
+        }
+        let marks = Format::Hodoku.render(&[sudoku.into()]).unwrap();
+        assert_eq!(marks.parse::<Sudoku>().unwrap(), CandidateGrid::from_sudoku(&sudoku).to_sudoku());
+        assert_eq!(sudoku.pretty().parse::<Sudoku>().unwrap(), sudoku);
+
+        let two = format!("{}\n{}\n", sudoku, Sudoku::new(*SOLUTION));
+        assert!(error(two.parse::<Sudoku>()).contains("expected one puzzle, found 2"));
+        assert!(error("".parse::<Sudoku>()).contains("found 0"));
+        assert!(error("123|456|78a\n".parse::<Sudoku>()).contains("unexpected character 'a'"));
+    }
+
+    #[test]
+    fn format_names_parse_case_insensitively() {
+        assert_eq!("HoDoKu".parse::<Format>().unwrap(), Format::Hodoku);
+        assert_eq!(" ss ".parse::<Format>().unwrap(), Format::Ss);
+        assert!(error("csv".parse::<Format>()).contains("unknown puzzle format 'csv'"));
+        #[cfg(feature = "std")]
+        assert_eq!(Format::from_extension(Path::new("puzzles.SDM")), Some(Format::Sdm));
+    }
+}
@@ -0,0 +1,302 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    candidates::{col_of, row_of, units as grid_units},
+    error::{Error, Result},
+    solver::Units,
+    sudoku::{Sudoku, N_CELLS},
+};
+
+/// Overlapping 9x9 grids on a shared canvas, as in Samurai Sudoku. Each
+/// grid keeps its own rows, columns and boxes, so a box shared by two grids
+/// is constrained by both.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gattai {
+    /// Top-left canvas cell of each grid, as (row, col).
+    origins: Vec<(usize, usize)>,
+    height: usize,
+    width: usize,
+    /// For each canvas cell in reading order, its index among the covered cells.
+    index: Vec<Option<usize>>,
+    units: Units,
+}
+
+impl Gattai {
+    /// Grids must start on multiples of three so that overlaps are whole boxes.
+    pub fn new(origins: Vec<(usize, usize)>) -> Result<Self> {
+        if origins.is_empty() {
+            return Err(Error::Format("a layout needs at least one grid".into()));
+        }
+        if let Some(origin) = origins.iter().find(|(row, col)| row % 3 != 0 || col % 3 != 0) {
+            return Err(Error::Format(format!(
+                "grid at {:?} is not aligned to the boxes of the canvas",
+                origin
+            )));
+        }
+        let height = origins.iter().map(|&(row, _)| row + 9).max().unwrap_or(9);
+        let width = origins.iter().map(|&(_, col)| col + 9).max().unwrap_or(9);
+
+        let mut index = vec![None; height * width];
+        for &(top, left) in &origins {
+            for cell in 0..N_CELLS {
+                index[(top + cell / 9) * width + left + cell % 9] = Some(0);
+            }
+        }
+        let mut count = 0;
+        for slot in index.iter_mut().flatten() {
+            *slot = count;
+            count += 1;
+        }
+
+        let mut units = Vec::new();
+        let mut seen = alloc::collections::BTreeSet::new();
+        for &(top, left) in &origins {
+            let at = |row: usize, col: usize| index[(top + row) * width + left + col].unwrap_or(0);
+            // A box shared by two grids is one unit
+            for unit in grid_units() {
+                let unit = unit.map(|cell| at(row_of(cell), col_of(cell)));
+                if seen.insert(unit) {
+                    units.push(unit);
+                }
+            }
+        }
+
+        Ok(Self {
+            origins,
+            height,
+            width,
+            index,
+            units: Units::new(count, units),
+        })
+    }
+
+    /// Five grids: four corners around a centre grid sharing a box with each.
+    pub fn samurai() -> Self {
+        Self::new(vec![(0, 0), (0, 12), (6, 6), (12, 0), (12, 12)]).expect("samurai layout is valid")
+    }
+
+    /// Two grids sharing one corner box.
+    pub fn twodoku() -> Self {
+        Self::new(vec![(0, 0), (6, 6)]).expect("twodoku layout is valid")
+    }
+
+    /// Four grids on a 12x12 canvas, each pair overlapping by two or four boxes.
+    pub fn butterfly() -> Self {
+        Self::new(vec![(0, 0), (0, 3), (3, 0), (3, 3)]).expect("butterfly layout is valid")
+    }
+
+    /// A named layout: `samurai`, `twodoku` or `butterfly`.
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "samurai" => Ok(Self::samurai()),
+            "twodoku" => Ok(Self::twodoku()),
+            "butterfly" => Ok(Self::butterfly()),
+            _ => Err(Error::Format(format!("unknown layout '{}'", name))),
+        }
+    }
+
+    pub fn origins(&self) -> &[(usize, usize)] {
+        &self.origins
+    }
+
+    /// Canvas size as (rows, columns).
+    pub fn size(&self) -> (usize, usize) {
+        (self.height, self.width)
+    }
+
+    /// Number of cells covered by at least one grid.
+    pub fn num_cells(&self) -> usize {
+        self.units.num_cells()
+    }
+
+    /// Index among the covered cells of canvas cell (row, col).
+    pub fn cell_at(&self, row: usize, col: usize) -> Option<usize> {
+        match row < self.height && col < self.width {
+            true => self.index[row * self.width + col],
+            false => None,
+        }
+    }
+
+    /// The covered cells of grid `grid` in reading order.
+    pub fn grid_cells(&self, grid: usize) -> [usize; N_CELLS] {
+        let (top, left) = self.origins[grid];
+        core::array::from_fn(|cell| self.cell_at(top + cell / 9, left + cell % 9).unwrap_or(0))
+    }
+
+    pub(crate) fn units(&self) -> &Units {
+        &self.units
+    }
+}
+
+/// Givens or a solution for a `Gattai` layout, one ASCII byte per covered
+/// cell with `.` for empty.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GattaiPuzzle {
+    pub layout: Gattai,
+    pub cells: Vec<u8>,
+}
+
+impl GattaiPuzzle {
+    pub fn empty(layout: Gattai) -> Self {
+        let cells = vec![b'.'; layout.num_cells()];
+        Self { layout, cells }
+    }
+
+    /// Parses either one 81 character line per grid, in the order of the
+    /// layout's grids, or the canvas drawn row by row with any character
+    /// outside the grids. Cells shared between grids must agree.
+    pub fn parse(layout: Gattai, text: &str) -> Result<Self> {
+        let lines = text
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .collect::<Vec<_>>();
+        let mut puzzle = Self::empty(layout);
+
+        let per_grid = lines.len() == puzzle.layout.origins.len()
+            && lines.iter().all(|line| line.trim().len() == N_CELLS);
+        if per_grid {
+            for (grid, line) in lines.iter().enumerate() {
+                let cells = puzzle.layout.grid_cells(grid);
+                for (&cell, &value) in cells.iter().zip(line.trim().as_bytes()) {
+                    puzzle.set(cell, value)?;
+                }
+            }
+            return Ok(puzzle);
+        }
+
+        let (height, width) = puzzle.layout.size();
+        if lines.len() != height {
+            return Err(Error::Format(format!(
+                "expected {} lines of one grid each or a {}x{} canvas, found {} lines",
+                puzzle.layout.origins.len(),
+                height,
+                width,
+                lines.len()
+            )));
+        }
+        for (row, line) in lines.iter().enumerate() {
+            for (col, &value) in line.as_bytes().iter().enumerate() {
+                if let Some(cell) = puzzle.layout.cell_at(row, col) {
+                    puzzle.set(cell, value)?;
+                }
+            }
+        }
+        Ok(puzzle)
+    }
+
+    fn set(&mut self, cell: usize, value: u8) -> Result<()> {
+        let value = match value {
+            b'1'..=b'9' => value,
+            _ => b'.',
+        };
+        match self.cells[cell] {
+            b'.' => self.cells[cell] = value,
+            current if value != b'.' && value != current => {
+                return Err(Error::Format(format!(
+                    "shared cell {} is given as both {} and {}",
+                    cell, current as char, value as char
+                )))
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Grid `grid` on its own.
+    pub fn grid(&self, grid: usize) -> Sudoku {
+        let cells = self.layout.grid_cells(grid);
+        Sudoku::new(cells.map(|cell| self.cells[cell]))
+    }
+
+    /// One 81 character line per grid, as read by `parse`.
+    pub fn to_lines(&self) -> String {
+        (0..self.layout.origins.len())
+            .map(|grid| self.grid(grid).to_string() + "\n")
+            .collect()
+    }
+
+    /// Whether every cell is filled and every grid is valid.
+    pub fn is_solved(&self) -> bool {
+        let empty = Sudoku::new([b'.'; N_CELLS]);
+        (0..self.layout.origins.len()).all(|grid| self.grid(grid).solves(&empty))
+    }
+
+    /// The canvas drawn like `Sudoku::pretty`, with boxes outside every grid left blank.
+    pub fn pretty(&self) -> String {
+        let (height, width) = self.layout.size();
+        let (box_rows, box_cols) = (height / 3, width / 3);
+        let covered = |br: Option<usize>, bc: Option<usize>| match (br, bc) {
+            (Some(br), Some(bc)) => self.layout.cell_at(br * 3, bc * 3).is_some(),
+            _ => false,
+        };
+        let before = |i: usize| i.checked_sub(1);
+
+        let mut result = String::new();
+        for br in 0..=box_rows {
+            // Border above box row `br`
+            let mut line = String::new();
+            for bc in 0..=box_cols {
+                let (above_left, above) = (covered(before(br), before(bc)), covered(before(br), Some(bc)));
+                let (below_left, below) = (covered(Some(br), before(bc)), covered(Some(br), Some(bc)));
+                let up = above_left || above;
+                let down = below_left || below;
+                let left = above_left || below_left;
+                let right = above || below;
+                line.push(match (up, down, left, right) {
+                    (true, true, true, true) => '┼',
+                    (false, true, true, true) => '┬',
+                    (true, false, true, true) => '┴',
+                    (true, true, false, true) => '├',
+                    (true, true, true, false) => '┤',
+                    (false, true, false, true) => '┌',
+                    (false, true, true, false) => '┐',
+                    (true, false, false, true) => '└',
+                    (true, false, true, false) => '┘',
+                    (false, false, false, false) => ' ',
+                    (_, _, false, false) => '│',
+                    (false, false, _, _) => '─',
+                });
+                if bc < box_cols {
+                    line.push_str(if right { "───────" } else { "       " });
+                }
+            }
+            result.push_str(line.trim_end());
+            result.push('\n');
+            if br == box_rows {
+                break;
+            }
+
+            for row in br * 3..br * 3 + 3 {
+                let mut line = String::new();
+                for bc in 0..=box_cols {
+                    let border = covered(Some(br), before(bc)) || covered(Some(br), Some(bc));
+                    line.push(if border { '│' } else { ' ' });
+                    if bc == box_cols {
+                        break;
+                    }
+                    line.push(' ');
+                    for col in bc * 3..bc * 3 + 3 {
+                        let ch = match self.layout.cell_at(row, col) {
+                            Some(cell) => match self.cells[cell] {
+                                value @ b'1'..=b'9' => value as char,
+                                _ => '.',
+                            },
+                            None => ' ',
+                        };
+                        line.push(ch);
+                        line.push(' ');
+                    }
+                }
+                result.push_str(line.trim_end());
+                result.push('\n');
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,84 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    solver::{Solver, SolverBasic},
+    sudoku::{Puzzle, Sudoku, N_CELLS},
+    transform::Transform,
+};
+
+/// A random puzzle with a unique solution and no redundant clues.
+///
+/// The solution is the solver's completion of the three diagonal boxes
+/// filled at random, scrambled by `Transform::random`. Its clues are then
+/// removed in random order, putting back each one whose removal would let in
+/// a second solution. Seed `rng` to get the same puzzle again.
+pub fn generate(rng: &mut impl Rng) -> Sudoku {
+    let solver = SolverBasic::new(2, true);
+    let mut state = solver.make_state();
+
+    // The diagonal boxes share no row or column, so any filling is consistent
+    let mut grid = [b'.'; N_CELLS];
+    for b in 0..3 {
+        let mut digits = *b"123456789";
+        digits.shuffle(rng);
+        for (i, &digit) in digits.iter().enumerate() {
+            grid[(b * 3 + i / 3) * 9 + b * 3 + i % 3] = digit;
+        }
+    }
+    let solution = solver
+        .solve(&Puzzle::new(&grid), &mut state)
+        .expect("filled diagonal boxes always have a completion")
+        .sudoku;
+    let mut puzzle = Transform::random(rng).apply(&solution);
+
+    let mut cells: [usize; N_CELLS] = core::array::from_fn(|cell| cell);
+    cells.shuffle(rng);
+    for cell in cells {
+        let clue = puzzle.grid[cell];
+        puzzle.grid[cell] = b'.';
+        let unique = solver
+            .solve(&Puzzle::new(&puzzle.grid), &mut state)
+            .is_some_and(|solution| solution.solutions == 1);
+        if !unique {
+            puzzle.grid[cell] = clue;
+        }
+    }
+    puzzle
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    fn solutions(solver: &SolverBasic, sudoku: &Sudoku) -> usize {
+        solver
+            .solve(&Puzzle::new(&sudoku.grid), &mut solver.make_state())
+            .map_or(0, |solution| solution.solutions)
+    }
+
+    #[test]
+    fn puzzles_are_unique_and_minimal() {
+        let solver = SolverBasic::new(2, true);
+        for seed in 0..5 {
+            let puzzle = generate(&mut ChaCha8Rng::seed_from_u64(seed));
+            assert_eq!(solutions(&solver, &puzzle), 1, "seed {}: {}", seed, puzzle);
+            for cell in (0..N_CELLS).filter(|&cell| puzzle.grid[cell] != b'.') {
+                let mut fewer = puzzle;
+                fewer.grid[cell] = b'.';
+                assert_eq!(solutions(&solver, &fewer), 2, "seed {}: clue {} is redundant", seed, cell);
+            }
+        }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_puzzle() {
+        for seed in 0..5 {
+            let first = generate(&mut ChaCha8Rng::seed_from_u64(seed));
+            assert_eq!(generate(&mut ChaCha8Rng::seed_from_u64(seed)), first, "seed {}", seed);
+            assert_ne!(generate(&mut ChaCha8Rng::seed_from_u64(seed + 100)), first, "seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,668 @@
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+use crate::{
+    candidates::{bit, sees, units, value_of, CandidateGrid, Mask, ALL},
+    solver::Solver,
+    sudoku::{Puzzle, Sudoku, N_CELLS},
+};
+
+/// Logical techniques, from easiest to hardest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Technique {
+    FullHouse,
+    HiddenSingle,
+    NakedSingle,
+    Pointing,
+    Claiming,
+    NakedPair,
+    HiddenPair,
+    NakedTriple,
+    XWing,
+    /// No technique applies; the value comes from the solver.
+    Solver,
+}
+
+impl Technique {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Technique::FullHouse => "Full House",
+            Technique::HiddenSingle => "Hidden Single",
+            Technique::NakedSingle => "Naked Single",
+            Technique::Pointing => "Pointing",
+            Technique::Claiming => "Claiming",
+            Technique::NakedPair => "Naked Pair",
+            Technique::HiddenPair => "Hidden Pair",
+            Technique::NakedTriple => "Naked Triple",
+            Technique::XWing => "X-Wing",
+            Technique::Solver => "Solver",
+        }
+    }
+}
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Place `value` (1-9) in `cell`.
+    Place { cell: usize, value: u8 },
+    /// Remove each `(cell, value)` candidate.
+    Eliminate(Vec<(usize, u8)>),
+}
+
+/// One deduction, with the cells that justify it.
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub technique: Technique,
+    pub action: Action,
+    pub because: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Hint {
+    Step(Step),
+    /// Entries (not givens) that differ from the unique solution.
+    Mistakes(Vec<usize>),
+    /// Every cell is filled in correctly.
+    Solved,
+    /// The givens do not have exactly one solution.
+    NotUnique,
+}
+
+/// Returns the easiest next deduction for `current`, which holds the
+/// `givens` plus the player's entries.
+///
+/// The solver checks that the givens have a unique solution, so it must be
+/// configured to look for at least two solutions, e.g. `SolverBasic::new(2, true)`.
+pub fn hint<S: Solver>(
+    solver: &S,
+    state: &mut S::State,
+    givens: &Sudoku,
+    current: &Sudoku,
+) -> Hint {
+    let candidates = CandidateGrid::from_sudoku(current);
+    hint_with_candidates(solver, state, givens, current, &candidates)
+}
+
+/// Like `hint`, but starting from the player's pencil marks for the empty
+/// cells of `current`, so eliminations already made are not suggested again.
+/// Ruling out the solution's digit in a cell counts as a mistake.
+pub fn hint_with_candidates<S: Solver>(
+    solver: &S,
+    state: &mut S::State,
+    givens: &Sudoku,
+    current: &Sudoku,
+    candidates: &CandidateGrid,
+) -> Hint {
+    let solution = match solver.solve(&Puzzle::new(&givens.grid), state) {
+        Some(solution) if solution.solutions == 1 => solution.sudoku,
+        _ => return Hint::NotUnique,
+    };
+
+    let mistakes = (0..N_CELLS)
+        .filter(|&cell| match value_of(current.grid[cell]) {
+            Some(_) => current.grid[cell] != solution.grid[cell],
+            // A cleared given is a mistake too
+            None => {
+                value_of(givens.grid[cell]).is_some()
+                    || !candidates.contains(cell, solution.grid[cell] - b'0')
+            }
+        })
+        .collect::<Vec<_>>();
+    if !mistakes.is_empty() {
+        return Hint::Mistakes(mistakes);
+    }
+
+    match next_step(current, candidates, &solution) {
+        Some(step) => Hint::Step(step),
+        None => Hint::Solved,
+    }
+}
+
+/// The easiest deduction for `current`, else a placement from `solution`,
+/// or `None` once every cell is filled.
+fn next_step(current: &Sudoku, candidates: &CandidateGrid, solution: &Sudoku) -> Option<Step> {
+    let grid = Grid::new(current, candidates);
+    grid.next_step().or_else(|| grid.fallback(solution))
+}
+
+/// How hard a puzzle is to solve by hand with the techniques `hint` knows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rating {
+    /// The hardest technique needed; `Technique::Solver` if the techniques
+    /// run out before the grid is filled.
+    pub hardest: Technique,
+    /// Deductions made, placements and eliminations alike.
+    pub steps: usize,
+}
+
+/// Rates `givens` by following hints from the start until the grid is
+/// filled, or `None` if the givens do not have a unique solution. Like
+/// `hint`, the solver must look for at least two solutions.
+pub fn rate<S: Solver>(solver: &S, state: &mut S::State, givens: &Sudoku) -> Option<Rating> {
+    match solver.solve(&Puzzle::new(&givens.grid), state) {
+        Some(solution) if solution.solutions == 1 => Some(rate_with_solution(givens, &solution.sudoku)),
+        _ => None,
+    }
+}
+
+/// Like `rate`, but with the unique `solution` of `givens` already known, so
+/// no solver runs and every step makes progress.
+pub fn rate_with_solution(givens: &Sudoku, solution: &Sudoku) -> Rating {
+    let mut current = *givens;
+    let mut candidates = CandidateGrid::from_sudoku(givens);
+    let mut rating = Rating {
+        hardest: Technique::FullHouse,
+        steps: 0,
+    };
+    while let Some(step) = next_step(&current, &candidates, solution) {
+        if let Action::Place { cell, value } = step.action {
+            current.grid[cell] = b'0' + value;
+        }
+        candidates.apply(&step.action);
+        rating.hardest = rating.hardest.max(step.technique);
+        rating.steps += 1;
+    }
+    rating
+}
+
+/// Placed values and the remaining candidates of the empty cells.
+struct Grid {
+    values: [u8; N_CELLS],
+    candidates: [Mask; N_CELLS],
+    units: [[usize; 9]; 27],
+}
+
+impl Grid {
+    fn new(sudoku: &Sudoku, pencil_marks: &CandidateGrid) -> Self {
+        let mut values = [0; N_CELLS];
+        for (value, &byte) in values.iter_mut().zip(sudoku.grid.iter()) {
+            *value = value_of(byte).unwrap_or(0);
+        }
+        let placed = CandidateGrid::from_sudoku(sudoku);
+        let mut candidates = [0; N_CELLS];
+        for (cell, mask) in candidates.iter_mut().enumerate() {
+            if values[cell] == 0 {
+                *mask = placed.mask(cell) & pencil_marks.mask(cell);
+            }
+        }
+        Self {
+            values,
+            candidates,
+            units: units(),
+        }
+    }
+
+    fn next_step(&self) -> Option<Step> {
+        self.full_house()
+            .or_else(|| self.hidden_single())
+            .or_else(|| self.naked_single())
+            .or_else(|| self.locked_candidates())
+            .or_else(|| self.naked_pair())
+            .or_else(|| self.hidden_pair())
+            .or_else(|| self.naked_triple())
+            .or_else(|| self.x_wing())
+    }
+
+    /// Reveals the solution for the empty cell with the fewest candidates.
+    fn fallback(&self, solution: &Sudoku) -> Option<Step> {
+        let cell = (0..N_CELLS)
+            .filter(|&cell| self.values[cell] == 0)
+            .min_by_key(|&cell| self.candidates[cell].count_ones())?;
+        Some(Step {
+            technique: Technique::Solver,
+            action: Action::Place {
+                cell,
+                value: solution.grid[cell] - b'0',
+            },
+            because: Vec::new(),
+        })
+    }
+
+    /// A placed cell seeing `cell` that holds `value`.
+    fn blocker(&self, cell: usize, value: u8) -> Option<usize> {
+        (0..N_CELLS).find(|&peer| self.values[peer] == value && sees(cell, peer))
+    }
+
+    fn empty_cells(&self, unit: &[usize; 9]) -> impl Iterator<Item = usize> + '_ {
+        let unit = *unit;
+        unit.into_iter().filter(move |&cell| self.values[cell] == 0)
+    }
+
+    fn full_house(&self) -> Option<Step> {
+        self.units.iter().find_map(|unit| {
+            let mut empty = self.empty_cells(unit);
+            let cell = empty.next()?;
+            if empty.next().is_some() || self.candidates[cell].count_ones() != 1 {
+                return None;
+            }
+            Some(Step {
+                technique: Technique::FullHouse,
+                action: Action::Place {
+                    cell,
+                    value: self.candidates[cell].trailing_zeros() as u8 + 1,
+                },
+                because: unit.iter().copied().filter(|&c| c != cell).collect(),
+            })
+        })
+    }
+
+    fn hidden_single(&self) -> Option<Step> {
+        for unit in &self.units {
+            for value in 1..=9 {
+                let mut places = self
+                    .empty_cells(unit)
+                    .filter(|&cell| self.candidates[cell] & bit(value) != 0);
+                let Some(cell) = places.next() else {
+                    continue;
+                };
+                if places.next().is_some() {
+                    continue;
+                }
+                let mut because = self
+                    .empty_cells(unit)
+                    .filter(|&other| other != cell)
+                    .filter_map(|other| self.blocker(other, value))
+                    .collect::<Vec<_>>();
+                because.sort_unstable();
+                because.dedup();
+                return Some(Step {
+                    technique: Technique::HiddenSingle,
+                    action: Action::Place { cell, value },
+                    because,
+                });
+            }
+        }
+        None
+    }
+
+    fn naked_single(&self) -> Option<Step> {
+        (0..N_CELLS)
+            .filter(|&cell| self.values[cell] == 0 && self.candidates[cell].count_ones() == 1)
+            .map(|cell| {
+                let value = self.candidates[cell].trailing_zeros() as u8 + 1;
+                let mut because = (1..=9)
+                    .filter(|&other| other != value)
+                    .filter_map(|other| self.blocker(cell, other))
+                    .collect::<Vec<_>>();
+                because.sort_unstable();
+                because.dedup();
+                Step {
+                    technique: Technique::NakedSingle,
+                    action: Action::Place { cell, value },
+                    because,
+                }
+            })
+            .next()
+    }
+
+    fn eliminations(&self, cells: impl IntoIterator<Item = usize>, mask: Mask) -> Vec<(usize, u8)> {
+        let mut eliminations = Vec::new();
+        for cell in cells {
+            let hits = self.candidates[cell] & mask;
+            for value in 1..=9 {
+                if self.values[cell] == 0 && hits & bit(value) != 0 {
+                    eliminations.push((cell, value));
+                }
+            }
+        }
+        eliminations
+    }
+
+    fn step(
+        technique: Technique,
+        eliminations: Vec<(usize, u8)>,
+        because: Vec<usize>,
+    ) -> Option<Step> {
+        match eliminations.is_empty() {
+            true => None,
+            false => Some(Step {
+                technique,
+                action: Action::Eliminate(eliminations),
+                because,
+            }),
+        }
+    }
+
+    /// Pointing: a digit confined to one line within a box; claiming: a digit
+    /// confined to one box within a line.
+    fn locked_candidates(&self) -> Option<Step> {
+        let (lines, boxes) = self.units.split_at(18);
+        for value in 1..=9 {
+            for unit in boxes {
+                if let Some(step) = self.locked(unit, lines, value, Technique::Pointing) {
+                    return Some(step);
+                }
+            }
+        }
+        for value in 1..=9 {
+            for unit in lines {
+                if let Some(step) = self.locked(unit, boxes, value, Technique::Claiming) {
+                    return Some(step);
+                }
+            }
+        }
+        None
+    }
+
+    fn locked(
+        &self,
+        unit: &[usize; 9],
+        others: &[[usize; 9]],
+        value: u8,
+        technique: Technique,
+    ) -> Option<Step> {
+        let places = self
+            .empty_cells(unit)
+            .filter(|&cell| self.candidates[cell] & bit(value) != 0)
+            .collect::<Vec<_>>();
+        if places.len() < 2 {
+            return None;
+        }
+        others.iter().find_map(|other| {
+            if !places.iter().all(|cell| other.contains(cell)) {
+                return None;
+            }
+            let rest = other.iter().copied().filter(|cell| !unit.contains(cell));
+            Self::step(
+                technique,
+                self.eliminations(rest, bit(value)),
+                places.clone(),
+            )
+        })
+    }
+
+    fn naked_pair(&self) -> Option<Step> {
+        for unit in &self.units {
+            let pairs = self
+                .empty_cells(unit)
+                .filter(|&cell| self.candidates[cell].count_ones() == 2)
+                .collect::<Vec<_>>();
+            for (i, &a) in pairs.iter().enumerate() {
+                for &b in &pairs[i + 1..] {
+                    if self.candidates[a] != self.candidates[b] {
+                        continue;
+                    }
+                    let rest = unit.iter().copied().filter(|&cell| cell != a && cell != b);
+                    let eliminations = self.eliminations(rest, self.candidates[a]);
+                    if let Some(step) = Self::step(Technique::NakedPair, eliminations, vec![a, b]) {
+                        return Some(step);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn hidden_pair(&self) -> Option<Step> {
+        for unit in &self.units {
+            let places = |value: u8| {
+                self.empty_cells(unit)
+                    .filter(|&cell| self.candidates[cell] & bit(value) != 0)
+                    .collect::<Vec<_>>()
+            };
+            for a in 1..=9 {
+                let cells = places(a);
+                if cells.len() != 2 {
+                    continue;
+                }
+                for b in a + 1..=9 {
+                    if places(b) != cells {
+                        continue;
+                    }
+                    let keep = bit(a) | bit(b);
+                    let eliminations = self.eliminations(cells.iter().copied(), ALL & !keep);
+                    if let Some(step) =
+                        Self::step(Technique::HiddenPair, eliminations, cells.clone())
+                    {
+                        return Some(step);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn naked_triple(&self) -> Option<Step> {
+        for unit in &self.units {
+            let cells = self
+                .empty_cells(unit)
+                .filter(|&cell| (2..=3).contains(&self.candidates[cell].count_ones()))
+                .collect::<Vec<_>>();
+            for (i, &a) in cells.iter().enumerate() {
+                for (j, &b) in cells.iter().enumerate().skip(i + 1) {
+                    for &c in &cells[j + 1..] {
+                        let mask = self.candidates[a] | self.candidates[b] | self.candidates[c];
+                        if mask.count_ones() != 3 {
+                            continue;
+                        }
+                        let rest = unit
+                            .iter()
+                            .copied()
+                            .filter(|&cell| ![a, b, c].contains(&cell));
+                        let eliminations = self.eliminations(rest, mask);
+                        if let Some(step) =
+                            Self::step(Technique::NakedTriple, eliminations, vec![a, b, c])
+                        {
+                            return Some(step);
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// A digit with exactly two places in each of two rows, in the same two
+    /// columns (or the same with rows and columns swapped).
+    fn x_wing(&self) -> Option<Step> {
+        let (rows, cols) = self.units[..18].split_at(9);
+        for value in 1..=9 {
+            for (lines, crosses) in [(rows, cols), (cols, rows)] {
+                let places = lines
+                    .iter()
+                    .map(|line| {
+                        line.iter()
+                            .enumerate()
+                            .filter(|&(_, &cell)| {
+                                self.values[cell] == 0 && self.candidates[cell] & bit(value) != 0
+                            })
+                            .map(|(i, _)| i)
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                for a in 0..9 {
+                    if places[a].len() != 2 {
+                        continue;
+                    }
+                    for b in a + 1..9 {
+                        if places[b] != places[a] {
+                            continue;
+                        }
+                        let rest = places[a].iter().flat_map(|&i| {
+                            crosses[i].iter().copied().filter(|&cell| {
+                                !lines[a].contains(&cell) && !lines[b].contains(&cell)
+                            })
+                        });
+                        let eliminations = self.eliminations(rest, bit(value));
+                        let because = places[a]
+                            .iter()
+                            .flat_map(|&i| [lines[a][i], lines[b][i]])
+                            .collect();
+                        if let Some(step) = Self::step(Technique::XWing, eliminations, because) {
+                            return Some(step);
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::solver::SolverBasic;
+
+    use super::*;
+
+    const PUZZLE: &[u8; N_CELLS] =
+        b"53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+    const SOLUTION: &[u8; N_CELLS] =
+        b"534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+    /// The next step for an otherwise empty grid holding `givens`, with the
+    /// `eliminated` candidates taken out of the pencil marks.
+    fn step(givens: &[(usize, u8)], eliminated: impl IntoIterator<Item = (usize, u8)>) -> Step {
+        let mut sudoku = Sudoku::new([b'.'; N_CELLS]);
+        for &(cell, value) in givens {
+            sudoku.grid[cell] = b'0' + value;
+        }
+        let mut candidates = CandidateGrid::new();
+        for (cell, value) in eliminated {
+            candidates.eliminate(cell, value);
+        }
+        next_step(&sudoku, &candidates, &Sudoku::new(*SOLUTION)).unwrap()
+    }
+
+    fn assert_step(step: Step, technique: Technique, action: Action, because: &[usize]) {
+        assert_eq!(step.technique, technique);
+        assert_eq!(step.action, action);
+        assert_eq!(step.because, because);
+    }
+
+    /// `value` ruled out of every cell in `cells`.
+    fn without(value: u8, cells: impl IntoIterator<Item = usize>) -> Vec<(usize, u8)> {
+        cells.into_iter().map(|cell| (cell, value)).collect()
+    }
+
+    #[test]
+    fn full_house() {
+        let mut sudoku = Sudoku::new(*SOLUTION);
+        sudoku.grid[40] = b'.';
+        let step = next_step(&sudoku, &CandidateGrid::from_sudoku(&sudoku), &Sudoku::new(*SOLUTION)).unwrap();
+        let because = [36, 37, 38, 39, 41, 42, 43, 44];
+        assert_step(step, Technique::FullHouse, Action::Place { cell: 40, value: 5 }, &because);
+    }
+
+    #[test]
+    fn hidden_single() {
+        // Ones in box 1, column 4, column 6 and box 3 leave r1c5 for the 1 of row 1
+        let step = step(&[(10, 1), (24, 1), (30, 1), (41, 1)], []);
+        assert_step(step, Technique::HiddenSingle, Action::Place { cell: 4, value: 1 }, &[10, 24, 30, 41]);
+    }
+
+    #[test]
+    fn naked_single() {
+        let step = step(&[], (1..=9).filter(|&value| value != 5).map(|value| (20, value)));
+        assert_step(step, Technique::NakedSingle, Action::Place { cell: 20, value: 5 }, &[]);
+    }
+
+    #[test]
+    fn pointing() {
+        let step = step(&[], without(1, [9, 10, 11, 18, 19, 20]));
+        assert_step(step, Technique::Pointing, Action::Eliminate(without(1, 3..9)), &[0, 1, 2]);
+    }
+
+    #[test]
+    fn claiming() {
+        let step = step(&[], without(1, 3..9));
+        assert_step(step, Technique::Claiming, Action::Eliminate(without(1, [9, 10, 11, 18, 19, 20])), &[0, 1, 2]);
+    }
+
+    #[test]
+    fn naked_pair() {
+        let step = step(&[], (3..=9).flat_map(|value| [(0, value), (1, value)]));
+        let eliminations = (2..9).flat_map(|cell| [(cell, 1), (cell, 2)]).collect();
+        assert_step(step, Technique::NakedPair, Action::Eliminate(eliminations), &[0, 1]);
+    }
+
+    #[test]
+    fn hidden_pair() {
+        let others = [1, 2, 4, 5, 6, 7, 8];
+        let step = step(&[], [without(1, others), without(2, others)].concat());
+        let eliminations = [0, 3].into_iter().flat_map(|cell| (3..=9).map(move |value| (cell, value))).collect();
+        assert_step(step, Technique::HiddenPair, Action::Eliminate(eliminations), &[0, 3]);
+    }
+
+    #[test]
+    fn naked_triple() {
+        let keep = [(0, [1, 2]), (3, [2, 3]), (6, [1, 3])];
+        let eliminated = keep
+            .iter()
+            .flat_map(|&(cell, pair)| (1..=9).filter(move |value| !pair.contains(value)).map(move |value| (cell, value)));
+        let step = step(&[], eliminated);
+        let rest = [1, 2, 4, 5, 7, 8];
+        let eliminations = rest.into_iter().flat_map(|cell| [(cell, 1), (cell, 2), (cell, 3)]).collect();
+        assert_step(step, Technique::NakedTriple, Action::Eliminate(eliminations), &[0, 3, 6]);
+    }
+
+    #[test]
+    fn x_wing() {
+        let others = [1, 2, 3, 5, 6, 7, 8];
+        let step = step(&[], [without(1, others), without(1, others.map(|col| 36 + col))].concat());
+        let rows = [1, 2, 3, 5, 6, 7, 8];
+        let eliminations = [0, 4].into_iter().flat_map(|col| rows.map(|row| (row * 9 + col, 1))).collect();
+        assert_step(step, Technique::XWing, Action::Eliminate(eliminations), &[0, 36, 4, 40]);
+    }
+
+    #[test]
+    fn solver_when_no_technique_applies() {
+        let step = step(&[], []);
+        assert_step(step, Technique::Solver, Action::Place { cell: 0, value: 5 }, &[]);
+    }
+
+    #[test]
+    fn hints_report_mistakes_and_ambiguity() {
+        let solver = SolverBasic::new(2, true);
+        let mut state = solver.make_state();
+        let givens = Sudoku::new(*PUZZLE);
+        assert!(matches!(hint(&solver, &mut state, &givens, &givens), Hint::Step(_)));
+
+        // The wrong 1 also rules out the 1 of its row, column and box, at r1c8, r3c1 and r7c3
+        let mut current = givens;
+        current.grid[2] = b'1';
+        assert!(matches!(
+            hint(&solver, &mut state, &givens, &current),
+            Hint::Mistakes(cells) if cells == [2, 7, 18, 56]
+        ));
+        let mut marks = CandidateGrid::from_sudoku(&givens);
+        marks.eliminate(2, 4);
+        assert!(matches!(
+            hint_with_candidates(&solver, &mut state, &givens, &givens, &marks),
+            Hint::Mistakes(cells) if cells == [2]
+        ));
+
+        let solution = Sudoku::new(*SOLUTION);
+        assert!(matches!(hint(&solver, &mut state, &givens, &solution), Hint::Solved));
+        let open = Sudoku::new([b'.'; N_CELLS]);
+        assert!(matches!(hint(&solver, &mut state, &open, &open), Hint::NotUnique));
+    }
+
+    #[test]
+    fn rating_follows_hints_to_the_end() {
+        let solver = SolverBasic::new(2, true);
+        let mut state = solver.make_state();
+        let (givens, solution) = (Sudoku::new(*PUZZLE), Sudoku::new(*SOLUTION));
+        let rating = rate(&solver, &mut state, &givens).unwrap();
+        assert_eq!(rating, rate_with_solution(&givens, &solution));
+        assert!(rating.hardest < Technique::Solver);
+        let empty = givens.grid.iter().filter(|&&byte| byte == b'.').count();
+        assert!(rating.steps >= empty);
+
+        let full = rate_with_solution(&solution, &solution);
+        assert_eq!(full, Rating { hardest: Technique::FullHouse, steps: 0 });
+        let mut almost = solution;
+        almost.grid[..2].copy_from_slice(b"..");
+        assert_eq!(rate_with_solution(&almost, &solution).steps, 2);
+
+        let open = Sudoku::new([b'.'; N_CELLS]);
+        assert_eq!(rate(&solver, &mut state, &open), None);
+        let rating = rate_with_solution(&open, &solution);
+        assert_eq!(rating.hardest, Technique::Solver);
+    }
+}
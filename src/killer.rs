@@ -0,0 +1,353 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    candidates::{col_of, parse_cell, row_of},
+    error::{Error, Result},
+    sudoku::{Sudoku, N_CELLS},
+};
+
+/// Labels used by `Killer::to_line`, one per cage in order.
+const LABELS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789!#$%&()*+-/;<=>?@[]^_{|}~";
+
+/// Connected cells whose digits are all different and add up to `sum`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cage {
+    /// Cell indices in reading order.
+    pub cells: Vec<usize>,
+    pub sum: u8,
+}
+
+/// The cage layout of a Killer Sudoku. Cells outside every cage are
+/// unconstrained apart from the usual rows, columns and boxes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Killer {
+    cages: Vec<Cage>,
+    cage_of: [Option<u8>; N_CELLS],
+}
+
+fn neighbours(cell: usize) -> impl Iterator<Item = usize> {
+    let (row, col) = (row_of(cell), col_of(cell));
+    [
+        (row > 0).then(|| cell - 9),
+        (row < 8).then(|| cell + 9),
+        (col > 0).then(|| cell - 1),
+        (col < 8).then(|| cell + 1),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+impl Killer {
+    /// Checks that the cages are connected, do not overlap and have sums
+    /// that `1..=9` can make with that many different digits.
+    pub fn new(mut cages: Vec<Cage>) -> Result<Self> {
+        let mut cage_of = [None; N_CELLS];
+        for (index, cage) in cages.iter_mut().enumerate() {
+            cage.cells.sort_unstable();
+            let size = cage.cells.len();
+            if size == 0 || size > 9 {
+                return Err(Error::Format(format!("cage {} has {} cells", index + 1, size)));
+            }
+            let (min, max) = ((1..=size).sum::<usize>(), (10 - size..=9).sum::<usize>());
+            if !(min..=max).contains(&(cage.sum as usize)) {
+                return Err(Error::Format(format!(
+                    "cage {} cannot add up to {} with {} cells",
+                    index + 1,
+                    cage.sum,
+                    size
+                )));
+            }
+            for &cell in &cage.cells {
+                if cell >= N_CELLS || cage_of[cell].is_some() {
+                    return Err(Error::Format(format!(
+                        "cell {} of cage {} is outside the grid or in another cage",
+                        cell,
+                        index + 1
+                    )));
+                }
+                cage_of[cell] = Some(index as u8);
+            }
+
+            // Flood fill from the first cell must reach the whole cage
+            let mut reached = vec![cage.cells[0]];
+            let mut next = 0;
+            while let Some(&cell) = reached.get(next) {
+                next += 1;
+                for neighbour in neighbours(cell) {
+                    if cage_of[neighbour] == Some(index as u8) && !reached.contains(&neighbour) {
+                        reached.push(neighbour);
+                    }
+                }
+            }
+            if reached.len() != size {
+                return Err(Error::Format(format!("cage {} is not connected", index + 1)));
+            }
+        }
+        Ok(Self { cages, cage_of })
+    }
+
+    pub fn cages(&self) -> &[Cage] {
+        &self.cages
+    }
+
+    /// Index into `cages` of the cage holding `cell`.
+    pub fn cage_of(&self, cell: usize) -> Option<usize> {
+        self.cage_of[cell].map(usize::from)
+    }
+
+    /// Parses either layout format, see `parse_line` and `parse_cages`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let first = text.lines().map(str::trim).find(|line| !line.is_empty());
+        match first.and_then(|line| line.split_once(':')) {
+            Some((labels, _)) if labels.trim().len() == N_CELLS => Self::parse_line(text.trim()),
+            _ => Self::parse_cages(text),
+        }
+    }
+
+    /// Parses the one-line form used by puzzle sites: 81 cage labels in
+    /// reading order, a colon, then the cage sums separated by commas.
+    ///
+    /// Each connected group of cells sharing a label is a cage, so labels may
+    /// be reused for cages that do not touch; `.` marks a cell outside every
+    /// cage. Sums are listed in reading order of each cage's first cell.
+    pub fn parse_line(line: &str) -> Result<Self> {
+        let Some((labels, sums)) = line.split_once(':') else {
+            return Err(Error::Format("expected cage labels and sums separated by ':'".into()));
+        };
+        let labels = labels.trim().as_bytes();
+        if labels.len() != N_CELLS {
+            return Err(Error::Format(format!(
+                "cage map is {} characters long, expected {}",
+                labels.len(),
+                N_CELLS
+            )));
+        }
+        let sums = sums
+            .split(',')
+            .map(|sum| {
+                sum.trim()
+                    .parse::<u8>()
+                    .map_err(|_| Error::Format(format!("invalid cage sum '{}'", sum.trim())))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut cells_of = Vec::new();
+        let mut seen = [false; N_CELLS];
+        for start in 0..N_CELLS {
+            if seen[start] || labels[start] == b'.' {
+                continue;
+            }
+            seen[start] = true;
+            let mut cells = vec![start];
+            let mut next = 0;
+            while let Some(&cell) = cells.get(next) {
+                next += 1;
+                for neighbour in neighbours(cell) {
+                    if !seen[neighbour] && labels[neighbour] == labels[start] {
+                        seen[neighbour] = true;
+                        cells.push(neighbour);
+                    }
+                }
+            }
+            cells_of.push(cells);
+        }
+        if cells_of.len() != sums.len() {
+            return Err(Error::Format(format!(
+                "cage map has {} cages but {} sums were given",
+                cells_of.len(),
+                sums.len()
+            )));
+        }
+
+        Self::new(
+            cells_of
+                .into_iter()
+                .zip(sums)
+                .map(|(cells, sum)| Cage { cells, sum })
+                .collect(),
+        )
+    }
+
+    /// Parses one cage per line: the sum followed by its cells in `r1c1`
+    /// notation, e.g. `17 r1c1 r1c2 r2c1`. Blank lines and lines starting
+    /// with `#` are skipped.
+    pub fn parse_cages(text: &str) -> Result<Self> {
+        let mut cages = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let sum = fields.next().and_then(|sum| sum.parse::<u8>().ok());
+            let cells = fields.map(parse_cell).collect::<Option<Vec<_>>>();
+            match (sum, cells) {
+                (Some(sum), Some(cells)) => cages.push(Cage { cells, sum }),
+                _ => {
+                    return Err(Error::Format(format!(
+                        "line {} is not a sum followed by r<row>c<col> cells",
+                        index + 1
+                    )))
+                }
+            }
+        }
+        Self::new(cages)
+    }
+
+    /// The one-line form read by `parse_line`.
+    pub fn to_line(&self) -> String {
+        let mut order = (0..self.cages.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&cage| self.cages[cage].cells[0]);
+
+        let mut labels = [b'.'; N_CELLS];
+        for (label, &cage) in order.iter().enumerate() {
+            for &cell in &self.cages[cage].cells {
+                labels[cell] = LABELS[label % LABELS.len()];
+            }
+        }
+        let sums = order
+            .iter()
+            .map(|&cage| self.cages[cage].sum.to_string())
+            .collect::<Vec<_>>();
+        format!("{}:{}", String::from_utf8_lossy(&labels), sums.join(","))
+    }
+
+    /// Whether `sudoku` is a valid grid that satisfies every cage.
+    pub fn is_solved_by(&self, sudoku: &Sudoku) -> bool {
+        let empty = Sudoku::new([b'.'; N_CELLS]);
+        sudoku.solves(&empty)
+            && self.cages.iter().all(|cage| {
+                let digits = cage.cells.iter().map(|&cell| sudoku.grid[cell]);
+                let sum = digits.clone().map(|digit| (digit - b'0') as usize).sum::<usize>();
+                let mut seen = 0u16;
+                digits.for_each(|digit| seen |= 1 << (digit - b'1'));
+                sum == cage.sum as usize && seen.count_ones() as usize == cage.cells.len()
+            })
+    }
+
+    fn border(&self, a: usize, b: Option<usize>) -> bool {
+        match b {
+            Some(b) => self.cage_of[a].is_none() || self.cage_of[a] != self.cage_of[b],
+            None => true,
+        }
+    }
+
+    /// Draws the cages with the sum in each cage's first cell and the digits
+    /// of `sudoku` below.
+    pub fn pretty(&self, sudoku: &Sudoku) -> String {
+        let cell_at = |row: usize, col: usize| (row < 9 && col < 9).then(|| row * 9 + col);
+        // Whether the edge above (row, col) or left of it is a cage border
+        let above = |row: usize, col: usize| match (row.checked_sub(1), cell_at(row, col)) {
+            (Some(up), Some(cell)) => self.border(cell, cell_at(up, col)),
+            _ => col < 9,
+        };
+        let left = |row: usize, col: usize| match (col.checked_sub(1), cell_at(row, col)) {
+            (Some(back), Some(cell)) => self.border(cell, cell_at(row, back)),
+            _ => row < 9,
+        };
+
+        let mut result = String::new();
+        for row in 0..=9 {
+            for col in 0..=9 {
+                let corner = above(row, col)
+                    || left(row, col)
+                    || (col > 0 && above(row, col - 1))
+                    || (row > 0 && left(row - 1, col));
+                result.push(if corner { '·' } else { ' ' });
+                if col < 9 {
+                    result.push_str(if above(row, col) { "────" } else { "    " });
+                }
+            }
+            result.push('\n');
+            if row == 9 {
+                break;
+            }
+
+            for line in 0..2 {
+                for col in 0..=9 {
+                    result.push(if left(row, col) { '│' } else { ' ' });
+                    let Some(cell) = cell_at(row, col) else {
+                        continue;
+                    };
+                    let text = match line {
+                        0 => match self.cage_of(cell) {
+                            Some(cage) if self.cages[cage].cells[0] == cell => self.cages[cage].sum.to_string(),
+                            _ => String::new(),
+                        },
+                        _ => match sudoku.grid[cell] {
+                            digit @ b'1'..=b'9' => format!(" {}", digit as char),
+                            _ => " .".into(),
+                        },
+                    };
+                    result.push_str(&format!("{:<4}", text));
+                }
+                result.push('\n');
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cages of `SOLUTION`, the only grid that satisfies them.
+    const LINE: &str = "AABBCCCDDABBEEFFDGHIIEJJKKGHILLMMKNGHHLLMMNNOPPPQQRROOSPTQURVVOSWTUURVVXWWWUYYXXX:\
+                                   14,19,24,7,13,8,18,15,22,6,15,30,15,18,16,17,16,28,11,8,10,19,20,22,14";
+    const SOLUTION: &[u8] =
+        b"534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+    fn error(result: Result<Killer>) -> String {
+        match result {
+            Err(Error::Format(message)) => message,
+            other => panic!("expected a format error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_line_reads_site_encodings() {
+        let killer = Killer::parse(LINE).unwrap();
+        assert_eq!(killer.cages().len(), 25);
+        assert_eq!(killer.cages()[0], Cage { cells: vec![0, 1, 9], sum: 14 });
+        assert_eq!(Killer::parse_line(&killer.to_line()).unwrap(), killer);
+        assert!(killer.is_solved_by(&Sudoku::new(SOLUTION.try_into().unwrap())));
+
+        // Labels only group touching cells, so `A` here is two cages
+        let reused = format!("AA.A{}:3,3", ".".repeat(N_CELLS - 4));
+        let killer = Killer::parse_line(&reused).unwrap();
+        assert_eq!(killer.cages().len(), 2);
+        assert_eq!(killer.cage_of(2), None);
+
+        assert!(error(Killer::parse_line(&LINE[1..])).contains("80 characters"));
+        assert!(error(Killer::parse_line(&format!("{},5", LINE))).contains("25 cages but 26 sums"));
+        assert!(error(Killer::parse_line(&LINE.replace("14,", "x,"))).contains("invalid cage sum 'x'"));
+        assert!(error(Killer::parse_line("AB")).contains("separated by ':'"));
+    }
+
+    #[test]
+    fn parse_cages_reads_one_cage_per_line() {
+        let killer = Killer::parse("# corner\n3 r1c1 R1C2\n\n17 r9c9 r8c9\n").unwrap();
+        assert_eq!(killer.cages(), [Cage { cells: vec![0, 1], sum: 3 }, Cage { cells: vec![71, 80], sum: 17 }]);
+        assert_eq!(killer.cage_of(80), Some(1));
+        assert!(error(Killer::parse_cages("3 r1c1\n4 r0c1 r1c2")).contains("line 2"));
+        assert!(error(Killer::parse_cages("3 r1c1 r1c2\nx r2c1")).contains("line 2"));
+    }
+
+    #[test]
+    fn new_rejects_bad_cages() {
+        let cage = |sum, cells: &[usize]| Cage { cells: cells.to_vec(), sum };
+        assert!(error(Killer::new(vec![cage(2, &[0, 1])])).contains("cannot add up to 2"));
+        assert!(error(Killer::new(vec![cage(18, &[0, 1])])).contains("cannot add up to 18"));
+        assert!(error(Killer::new(vec![cage(3, &[0, 1]), cage(4, &[1, 2])])).contains("in another cage"));
+        assert!(error(Killer::new(vec![cage(3, &[0, 2])])).contains("not connected"));
+        assert!(error(Killer::new(vec![cage(3, &[])])).contains("0 cells"));
+        assert!(error(Killer::new(vec![cage(3, &[80, 81])])).contains("outside the grid"));
+        assert!(Killer::new(vec![cage(45, &[0, 1, 2, 9, 10, 11, 18, 19, 20])]).is_ok());
+    }
+}
@@ -0,0 +1,303 @@
+use alloc::{format, vec, vec::Vec};
+
+use crate::{
+    candidates::{box_of, col_of, row_of},
+    error::{Error, Result},
+    sudoku::{Sudoku, N_CELLS},
+};
+
+/// The regions a grid must satisfy: rows and columns, nine boxes (the
+/// standard 3x3 ones or irregular jigsaw pieces) and any number of extra
+/// regions that must also hold each digit once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    boxes: [u8; N_CELLS],
+    regions: Vec<[u8; 9]>,
+    /// For each cell, indices into `regions`.
+    regions_of: Vec<Vec<u8>>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl Layout {
+    pub fn standard() -> Self {
+        let mut boxes = [0; N_CELLS];
+        for (cell, sub) in boxes.iter_mut().enumerate() {
+            *sub = box_of(cell) as u8;
+        }
+        Self {
+            boxes,
+            regions: Vec::new(),
+            regions_of: vec![Vec::new(); N_CELLS],
+        }
+    }
+
+    /// Irregular boxes from an 81 character map in reading order. Each of
+    /// nine labels must cover exactly nine cells; boxes are numbered in
+    /// order of first appearance.
+    pub fn jigsaw(map: &str) -> Result<Self> {
+        let map = map.trim().as_bytes();
+        if map.len() != N_CELLS {
+            return Err(Error::Format(format!(
+                "region map is {} characters long, expected {}",
+                map.len(),
+                N_CELLS
+            )));
+        }
+        let mut labels = Vec::new();
+        let mut sizes = [0; 9];
+        let mut boxes = [0; N_CELLS];
+        for (sub, &label) in boxes.iter_mut().zip(map) {
+            let index = match labels.iter().position(|&seen| seen == label) {
+                Some(index) => index,
+                None if labels.len() < 9 => {
+                    labels.push(label);
+                    labels.len() - 1
+                }
+                None => return Err(Error::Format("region map has more than nine regions".into())),
+            };
+            sizes[index] += 1;
+            *sub = index as u8;
+        }
+        if let Some(index) = sizes.iter().position(|&size| size != 9) {
+            return Err(Error::Format(format!(
+                "region '{}' has {} cells, expected 9",
+                labels.get(index).map_or('?', |&label| label as char),
+                sizes[index]
+            )));
+        }
+        Ok(Self {
+            boxes,
+            ..Self::standard()
+        })
+    }
+
+    /// Adds a region of nine different cells.
+    pub fn with_region(mut self, cells: [usize; 9]) -> Result<Self> {
+        let mut seen = [false; N_CELLS];
+        for &cell in &cells {
+            if cell >= N_CELLS || seen[cell] {
+                return Err(Error::Format(format!(
+                    "region {:?} repeats a cell or is outside the grid",
+                    cells
+                )));
+            }
+            seen[cell] = true;
+        }
+        let index = self.regions.len() as u8;
+        for &cell in &cells {
+            self.regions_of[cell].push(index);
+        }
+        self.regions.push(cells.map(|cell| cell as u8));
+        Ok(self)
+    }
+
+    fn with_regions(self, regions: impl IntoIterator<Item = [usize; 9]>) -> Self {
+        regions.into_iter().fold(self, |layout, cells| {
+            layout.with_region(cells).expect("built-in regions are valid")
+        })
+    }
+
+    /// Both main diagonals, as in Sudoku-X.
+    pub fn with_diagonals(self) -> Self {
+        self.with_regions([
+            core::array::from_fn(|i| i * 10),
+            core::array::from_fn(|i| i * 8 + 8),
+        ])
+    }
+
+    /// The four 3x3 windows of Hyper Sudoku, one cell in from each corner.
+    pub fn with_hyper(self) -> Self {
+        self.with_regions(
+            [(1, 1), (1, 5), (5, 1), (5, 5)]
+                .map(|(top, left)| core::array::from_fn(|i| (top + i / 3) * 9 + left + i % 3)),
+        )
+    }
+
+    /// The four hyper windows plus the five regions they imply, which
+    /// wrap around the rows and columns between and outside the windows.
+    pub fn with_windoku(self) -> Self {
+        let groups = [[0, 4, 8], [1, 2, 3], [5, 6, 7]];
+        let mut regions = Vec::new();
+        for rows in &groups {
+            for cols in &groups {
+                regions.push(core::array::from_fn(|i| rows[i / 3] * 9 + cols[i % 3]));
+            }
+        }
+        self.with_regions(regions)
+    }
+
+    /// Parses a variant name: `standard`, `x`, `hyper`, `windoku` or
+    /// `jigsaw:<81 character region map>`, combined with `+`, e.g. `x+hyper`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut layout = Self::standard();
+        let mut jigsaw = None;
+        for part in spec.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "standard" => {}
+                "x" => layout = layout.with_diagonals(),
+                "hyper" => layout = layout.with_hyper(),
+                "windoku" => layout = layout.with_windoku(),
+                name => match name.strip_prefix("jigsaw:") {
+                    Some(_) => jigsaw = Some(Self::jigsaw(&part["jigsaw:".len()..])?.boxes),
+                    None => return Err(Error::Format(format!("unknown variant '{}'", part))),
+                },
+            }
+        }
+        if let Some(boxes) = jigsaw {
+            layout.boxes = boxes;
+        }
+        Ok(layout)
+    }
+
+    /// Reads a `variant: <spec>` header line, optionally starting with `#`.
+    /// Returns `None` if the line is not a variant header.
+    pub fn from_header(line: &str) -> Option<Result<Self>> {
+        let line = line.trim().trim_start_matches('#').trim_start();
+        let spec = line.strip_prefix("variant")?.trim_start().strip_prefix([':', '='])?;
+        Some(Self::parse(spec.trim()))
+    }
+
+    /// Whether this is the plain 9x9 layout.
+    pub fn is_standard(&self) -> bool {
+        *self == Self::standard()
+    }
+
+    /// Index of the box holding `cell`.
+    pub fn box_of(&self, cell: usize) -> usize {
+        self.boxes[cell] as usize
+    }
+
+    /// The extra regions, each as nine cell indices.
+    pub fn regions(&self) -> impl Iterator<Item = [usize; 9]> + '_ {
+        self.regions.iter().map(|cells| cells.map(usize::from))
+    }
+
+    /// Indices into `regions` of the extra regions holding `cell`.
+    pub(crate) fn regions_of(&self, cell: usize) -> &[u8] {
+        &self.regions_of[cell]
+    }
+
+    pub(crate) fn num_regions(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Whether `sudoku` is a complete grid with each digit once in every
+    /// row, column, box and extra region.
+    pub fn is_solved_by(&self, sudoku: &Sudoku) -> bool {
+        let mut masks = vec![0u16; 27 + self.regions.len()];
+        for (cell, &value) in sudoku.grid.iter().enumerate() {
+            if !(b'1'..=b'9').contains(&value) {
+                return false;
+            }
+            let bit = 1 << (value - b'1');
+            masks[row_of(cell)] |= bit;
+            masks[9 + col_of(cell)] |= bit;
+            masks[18 + self.box_of(cell)] |= bit;
+            for &region in self.regions_of(cell) {
+                masks[27 + region as usize] |= bit;
+            }
+        }
+        masks.iter().all(|&mask| mask == 0x1ff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use crate::solver::{Solver, SolverBasic};
+    use crate::sudoku::Puzzle;
+
+    use super::*;
+
+    const JIGSAW: &str = "AAABBECCCAAABBECCCAADBBECCFDADBEEECFDDDBEFFFFDDDBEEFFFGGGGGGIIIGHHHHHIIIGGHHHHIII";
+
+    /// Solves `puzzle` under `layout`, checking there is exactly one solution.
+    fn assert_solves(layout: Layout, puzzle: &[u8], solution: &[u8]) {
+        let solver = SolverBasic::new(2, true).layout(layout.clone());
+        let info = solver.solve(&Puzzle::new(puzzle), &mut solver.make_state()).unwrap();
+        assert_eq!(info.solutions, 1);
+        assert_eq!(&info.sudoku.grid[..], solution);
+        assert!(layout.is_solved_by(&info.sudoku));
+    }
+
+    fn error(result: Result<Layout>) -> String {
+        match result {
+            Err(Error::Format(message)) => message,
+            Err(err) => panic!("expected a format error, got {}", err),
+            Ok(_) => panic!("expected a format error"),
+        }
+    }
+
+    #[test]
+    fn solves_jigsaw() {
+        assert_solves(
+            Layout::parse(&format!("jigsaw:{}", JIGSAW)).unwrap(),
+            b"4......6.69............5..2.2.....4..3...............8..5.4..9...47...1..6.13....",
+            b"451923867693874521876415932528367149239581674147692358315248796984756213762139485",
+        );
+    }
+
+    #[test]
+    fn solves_x() {
+        assert_solves(
+            Layout::parse("x").unwrap(),
+            b".......8.3.5...........3.54.2.....6..3.........7....3............85.9.1..4.71....",
+            b"412957683395864127786123954124375869539681472867492531951236748278549316643718295",
+        );
+    }
+
+    #[test]
+    fn solves_hyper() {
+        assert_solves(
+            Layout::parse("hyper").unwrap(),
+            b"4......263.1.......2.....8........4..4...................2....8..75...1..3.67....",
+            b"458197326391826574726453981875361249249785163613942857564219738987534612132678495",
+        );
+    }
+
+    #[test]
+    fn solves_windoku() {
+        let layout = Layout::parse("Windoku").unwrap();
+        assert_eq!(layout.num_regions(), 9);
+        assert_solves(
+            layout,
+            b"4......2.3.........2...1.38.7........3.9....7.......5.....6......72...1..6....8..",
+            b"458793126391826574726451938675314289132985467849672351913568742587249613264137895",
+        );
+    }
+
+    #[test]
+    fn parses_combined_variants_and_headers() {
+        let layout = Layout::parse(&format!("x + hyper + jigsaw:{}", JIGSAW)).unwrap();
+        assert_eq!(layout.num_regions(), 6);
+        // Boxes are numbered by first appearance, so `E` is the third
+        assert_eq!(layout.box_of(5), 2);
+        assert_eq!(layout.regions_of(10), [0, 2]);
+        assert!(Layout::parse("standard").unwrap().is_standard());
+
+        let header = Layout::from_header("# variant: x+hyper").unwrap().unwrap();
+        assert_eq!(header, Layout::standard().with_diagonals().with_hyper());
+        assert!(Layout::from_header("variant=windoku").unwrap().is_ok());
+        assert!(Layout::from_header("53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79").is_none());
+        assert!(Layout::from_header("variant: sudoku").unwrap().is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_region_maps() {
+        assert!(error(Layout::jigsaw(&JIGSAW[1..])).contains("80 characters long"));
+        let ten = format!("J{}", &JIGSAW[1..]);
+        assert!(error(Layout::jigsaw(&ten)).contains("more than nine regions"));
+        let uneven = format!("B{}", &JIGSAW[1..]);
+        assert!(error(Layout::jigsaw(&uneven)).contains("region 'B' has 10 cells"));
+        assert!(error(Layout::parse("x+jigsaw:AB")).contains("2 characters long"));
+        assert!(error(Layout::parse("x+samurai")).contains("unknown variant 'samurai'"));
+        assert!(error(Layout::standard().with_region([0, 1, 2, 3, 4, 5, 6, 7, 7])).contains("repeats a cell"));
+        assert!(error(Layout::standard().with_region([0, 1, 2, 3, 4, 5, 6, 7, 81])).contains("outside the grid"));
+    }
+}
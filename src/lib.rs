@@ -1,13 +1,93 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod alphabet;
+mod batch;
+#[cfg(feature = "std")]
+mod bench;
+mod candidates;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod canonical;
+mod constraint;
+#[cfg(feature = "std")]
+mod dedup;
+#[cfg(feature = "std")]
+mod diff;
+mod error;
+mod format;
+mod gattai;
+mod generate;
+mod hint;
+mod killer;
+mod layout;
+#[cfg(feature = "std")]
+mod minimize;
+#[cfg(feature = "std")]
+mod pipeline;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "std")]
 mod reader;
+mod sat;
+#[cfg(feature = "serve")]
+mod serve;
 mod solver;
 mod sudoku;
+mod transform;
 mod types;
+mod variant;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
 mod worker;
+#[cfg(feature = "std")]
 mod writer;
 
+pub use crate::alphabet::Alphabet;
+pub use crate::batch::{parse_puzzles, parse_puzzles_with};
+#[cfg(feature = "std")]
+pub use crate::batch::{solve_batch, solve_batch_bytes, BatchReport};
+#[cfg(feature = "std")]
+pub use crate::bench::{bench, Baseline, BenchConfig, BenchResult, Corpus, Estimate};
+pub use crate::candidates::{CandidateGrid, Mask};
+pub use crate::constraint::{AntiKing, AntiKnight, Arrow, Constraint, Dot, Kropki, NonConsecutive, Thermo};
+#[cfg(feature = "std")]
+pub use crate::dedup::{Dedup, DuplicateClass};
+#[cfg(feature = "std")]
+pub use crate::diff::{DiffHarness, DiffReport, Disagreement, Verdict};
+pub use crate::error::{Error, Result};
+pub use crate::format::{Format, Record};
+pub use crate::gattai::{Gattai, GattaiPuzzle};
+pub use crate::generate::generate;
+pub use crate::hint::{
+    hint, hint_with_candidates, rate, rate_with_solution, Action, Hint, Rating, Step, Technique,
+};
+pub use crate::killer::{Cage, Killer};
+pub use crate::layout::Layout;
+#[cfg(feature = "std")]
+pub use crate::minimize::{minimize_batch, MinimizeResult};
+#[cfg(feature = "std")]
+pub use crate::pipeline::{Pipeline, PipelineHandle, Progress, RunReport};
+#[cfg(feature = "std")]
 pub use crate::reader::Reader;
-pub use crate::solver::{Solver, SolverBasic};
+pub use crate::sat::{decode, parse_model, var, AtMostOne, Cnf, Encoder, Encoding};
+#[cfg(feature = "serve")]
+pub use crate::serve::{Service, ServiceHandle, ServiceStats};
+#[cfg(feature = "std")]
+pub use crate::solver::SolverSat;
+pub use crate::solver::{
+    Budget, GattaiOutcome, GattaiSolution, Outcome, SolutionInfo, Solutions, Solver, SolverBasic, SolverGattai,
+    SolverKiller,
+};
 pub use crate::sudoku::{Puzzle, Sudoku};
-pub use crate::types::{ChunkStats, PuzzleChunk, SolvedChunk};
+pub use crate::transform::Transform;
+pub use crate::types::{CancelToken, ChunkStats, SolvedChunk};
+#[cfg(feature = "std")]
+pub use crate::types::{PuzzleChunk, Source};
+pub use crate::variant::VariantPuzzle;
+#[cfg(feature = "std")]
 pub use crate::worker::Worker;
-pub use crate::writer::Writer;
+#[cfg(feature = "std")]
+pub use crate::writer::{ChunkCallback, Writer};
@@ -1,20 +1,93 @@
-use clap::Parser;
-use crossbeam::channel;
-use memmap2::MmapOptions;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use num_format::{Locale, ToFormattedString};
-use oronsay::{ChunkStats, Reader, SolverBasic, Worker, Writer};
-use std::fs::File;
-use std::path::PathBuf;
-use std::sync::Arc;
+use oronsay::{
+    bench, minimize_batch, parse_puzzles, Action, Alphabet, AtMostOne, Baseline, BenchConfig, BenchResult, Budget,
+    CandidateGrid, Corpus, Dedup, DiffHarness, Encoding, Error, Format, Gattai, GattaiPuzzle, Hint, Killer, Layout,
+    Pipeline, Puzzle, RunReport, Solver, SolverBasic, SolverGattai, SolverKiller, SolverSat, Sudoku, Transform,
+    VariantPuzzle,
+};
+#[cfg(feature = "serve")]
+use oronsay::Service;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
 use std::time::Duration;
-use std::{io, thread};
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
-struct Args {
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    solve: SolveArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List every solution of a single puzzle
+    Solutions(SolutionsArgs),
+    /// Run several solvers over the same input and report disagreements
+    DiffSolvers(DiffArgs),
+    /// Benchmark solvers on built-in or given corpora
+    Bench(BenchArgs),
+    /// Drop puzzles that are equivalent up to symmetry
+    Dedup(DedupArgs),
+    /// Write random isomorphs of each puzzle
+    Shuffle(ShuffleArgs),
+    /// Strip redundant clues and report how many each puzzle had
+    Minimize(MinimizeArgs),
+    /// Suggest the next logical step for a partially filled puzzle
+    Hint(HintArgs),
+    /// Solve a Killer Sudoku given its cage layout
+    Killer(KillerArgs),
+    /// Solve a puzzle with variant constraints described in JSON or text
+    SolveVariant(SolveVariantArgs),
+    /// Solve a Samurai or other puzzle of overlapping grids
+    Gattai(GattaiArgs),
+    /// Write a puzzle as DIMACS CNF for a SAT solver
+    Cnf(CnfArgs),
+    /// Convert puzzles between file formats
+    Convert(ConvertArgs),
+    /// Answer solve, uniqueness and rating requests over HTTP
+    #[cfg(feature = "serve")]
+    Serve(ServeArgs),
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum SolverKind {
+    /// Backtracking with the most-constrained-cell heuristic
+    Basic,
+    /// Backtracking in cell order
+    BasicNoMcv,
+    /// CNF handed to the first SAT solver found on PATH
+    Sat,
+}
+
+impl std::fmt::Display for SolverKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => write!(f, "{}", value.get_name()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Args)]
+struct SolveArgs {
     /// Input file
-    #[clap(short, long)]
-    infile: PathBuf,
+    #[clap(short, long, required = true)]
+    infile: Option<PathBuf>,
 
     /// Output file
     #[clap(short, long)]
@@ -32,31 +105,74 @@ struct Args {
     #[clap(short, long)]
     no_hash: bool,
 
+    /// Give up on a puzzle after visiting this many search nodes
+    #[clap(long)]
+    max_nodes: Option<usize>,
+
+    /// Variant rules: standard, x, hyper, windoku or jigsaw:<region map>,
+    /// combined with '+'; defaults to a "variant: ..." header in the input
+    #[clap(long)]
+    variant: Option<String>,
+
+    /// Give up on a puzzle after this many milliseconds
+    #[clap(long)]
+    timeout_ms: Option<u64>,
+
+    /// Characters that mean an empty cell; any other non-digit is an error
+    #[clap(long, default_value = ".0")]
+    empty: String,
+
+    /// Treat every character but 1-9 as an empty cell without checking
+    #[clap(long, conflicts_with = "empty")]
+    permissive: bool,
+
+    /// Write this character for empty cells in the output
+    #[clap(long)]
+    output_empty: Option<char>,
+
     /// Verbose output
     #[clap(short, long)]
     verbose: bool,
 }
 
+#[derive(Args)]
+struct SolutionsArgs {
+    /// Puzzle as an 81 character string
+    puzzle: String,
+
+    /// Stop after this many solutions
+    #[clap(short, long, default_value_t = 1000)]
+    max: usize,
+
+    /// Variant rules, as for solving a file
+    #[clap(long)]
+    variant: Option<String>,
+
+    /// Print each solution as a grid
+    #[clap(short, long)]
+    pretty: bool,
+}
+
 fn get_num_threads() -> usize {
     thread::available_parallelism()
         .map(|n| n.get())
         .unwrap_or(1)
 }
 
-fn display_stats(
-    stats: &ChunkStats,
-    hash: Option<String>,
-    elapsed: Duration,
-    num_threads: usize,
-) -> io::Result<()> {
+fn display_stats(report: &RunReport) {
+    let stats = &report.stats;
+    let elapsed = report.elapsed;
+    // Averages over no puzzles at all come out as zero rather than panicking
+    let count = stats.puzzles.max(1) as f64;
+
     let real_rate = stats.puzzles as f64 / elapsed.as_secs_f64();
-    let real_avg_time = elapsed / stats.puzzles as u32;
+    let real_avg_time = elapsed.div_f64(count);
 
     let solver_rate = stats.puzzles as f64 / stats.elapsed.as_secs_f64();
-    let solver_avg_time = stats.elapsed / stats.puzzles as u32;
+    let solver_avg_time = stats.elapsed.div_f64(count);
 
-    let guess_rate = stats.guesses as f32 / stats.puzzles as f32;
-    let no_guess_percent = (stats.no_guesses as f32 / stats.puzzles as f32) * 100.0;
+    let guess_rate = stats.guesses as f64 / count;
+    let no_guess_percent = (stats.no_guesses as f64 / count) * 100.0;
 
     println!(
         "   # Puzzles: {}, No Guesses: {:.2}%, Avg Guesses: {:.2}",
@@ -76,44 +192,809 @@ fn display_stats(
         stats.elapsed,
         (solver_rate as u32).to_formatted_string(&Locale::en),
         solver_avg_time,
-        num_threads
+        report.threads
     );
 
-    match hash {
+    if stats.gave_up > 0 {
+        println!(
+            "    Gave Up: {} puzzles exceeded the search budget",
+            stats.gave_up.to_formatted_string(&Locale::en)
+        );
+    }
+
+    match report.hash {
         Some(ref h) => println!("SHA-256 Hash: {}", h),
         None => println!("SHA-256 Hash: Not computed"),
     };
+}
 
-    Ok(())
+#[derive(Args)]
+struct DiffArgs {
+    /// Input file
+    #[clap(short, long)]
+    infile: PathBuf,
+
+    /// Report file, defaults to stdout
+    #[clap(short, long)]
+    outfile: Option<PathBuf>,
+
+    /// Solvers to compare
+    #[clap(short, long, value_delimiter = ',', default_values_t = [SolverKind::Basic, SolverKind::BasicNoMcv])]
+    solvers: Vec<SolverKind>,
+
+    /// Number of worker threads
+    #[clap(short = 't', long = "threads")]
+    num_threads: Option<usize>,
+
+    /// Skip puzzles where a solver visits more than this many search nodes
+    #[clap(long)]
+    max_nodes: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum BenchMode {
+    Single,
+    Multi,
+    Both,
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    /// Solvers to benchmark
+    #[clap(short, long, value_delimiter = ',', default_values_t = [SolverKind::Basic])]
+    solvers: Vec<SolverKind>,
+
+    /// Built-in corpus names (easy, medium, hard) or puzzle files
+    #[clap(short, long, value_delimiter = ',', default_values = ["easy", "medium", "hard"])]
+    corpus: Vec<String>,
+
+    /// Timed trials per solver and corpus
+    #[clap(long, default_value_t = 10)]
+    trials: usize,
+
+    /// Untimed runs before the trials
+    #[clap(long, default_value_t = 2)]
+    warmup: usize,
+
+    /// Run single-threaded, multi-threaded or both
+    #[clap(short, long, value_enum, default_value_t = BenchMode::Both)]
+    mode: BenchMode,
+
+    /// Number of threads for multi-threaded runs
+    #[clap(short = 't', long = "threads")]
+    num_threads: Option<usize>,
+
+    /// Compare against results saved with --save
+    #[cfg(feature = "serde")]
+    #[clap(short, long)]
+    baseline: Option<PathBuf>,
+
+    /// Save results as JSON for later comparison
+    #[cfg(feature = "serde")]
+    #[clap(long)]
+    save: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct DedupArgs {
+    /// Input file
+    #[clap(short, long)]
+    infile: PathBuf,
+
+    /// Output file, defaults to stdout
+    #[clap(short, long)]
+    outfile: Option<PathBuf>,
+
+    /// Write each class with duplicates as "count canonical first-index"
+    #[clap(short, long)]
+    report: Option<PathBuf>,
+
+    /// Number of worker threads
+    #[clap(short = 't', long = "threads")]
+    num_threads: Option<usize>,
+
+    /// Characters that mean an empty cell; any other non-digit is an error
+    #[clap(long, default_value = ".0")]
+    empty: String,
+
+    /// Treat every character but 1-9 as an empty cell without checking
+    #[clap(long, conflicts_with = "empty")]
+    permissive: bool,
+}
+
+#[derive(Args)]
+struct ShuffleArgs {
+    /// Input file
+    #[clap(short, long)]
+    infile: PathBuf,
+
+    /// Output file, defaults to stdout
+    #[clap(short, long)]
+    outfile: Option<PathBuf>,
+
+    /// Seed for the random transforms
+    #[clap(short, long, default_value_t = 0)]
+    seed: u64,
+
+    /// Isomorphs to write per input puzzle
+    #[clap(short = 'n', long, default_value_t = 1)]
+    count: usize,
+}
+
+#[derive(Args)]
+struct MinimizeArgs {
+    /// Input file
+    #[clap(short, long)]
+    infile: PathBuf,
+
+    /// Output file of "minimal,redundant-clues" lines, defaults to stdout
+    #[clap(short, long)]
+    outfile: Option<PathBuf>,
+
+    /// Number of worker threads
+    #[clap(short = 't', long = "threads")]
+    num_threads: Option<usize>,
+}
+
+#[derive(Args)]
+struct HintArgs {
+    /// Puzzle givens as an 81 character string
+    givens: String,
+
+    /// Current grid, givens plus entries, defaults to the givens
+    current: Option<String>,
+
+    /// Pencil marks for the current grid as a 729 character candidate string
+    #[clap(short, long)]
+    candidates: Option<String>,
+
+    /// Print the candidates the hint was found from
+    #[clap(short, long)]
+    pencil_marks: bool,
+}
+
+#[derive(Args)]
+struct KillerArgs {
+    /// Cage layout, either one "sum r1c1 r1c2 ..." line per cage or the
+    /// one-line "labels:sums" form
+    #[clap(short, long)]
+    infile: PathBuf,
+
+    /// Extra givens as an 81 character string
+    #[clap(short, long)]
+    givens: Option<String>,
+
+    /// Print the solution with its cages
+    #[clap(short, long)]
+    pretty: bool,
+}
+
+#[derive(Args)]
+struct SolveVariantArgs {
+    /// Puzzle description with grid, variant and constraints
+    #[clap(short, long)]
+    infile: PathBuf,
+
+    /// Print the solution as a grid
+    #[clap(short, long)]
+    pretty: bool,
+}
+
+#[derive(Args)]
+struct GattaiArgs {
+    /// Givens as one 81 character line per grid or the whole canvas row by row
+    #[clap(short, long)]
+    infile: PathBuf,
+
+    /// How the grids overlap: samurai, twodoku or butterfly
+    #[clap(short, long, default_value = "samurai")]
+    layout: String,
+
+    /// Print the solution as a grid
+    #[clap(short, long)]
+    pretty: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EncodingKind {
+    /// Each cell holds a digit, no unit holds a digit twice
+    Minimal,
+    /// Minimal plus each cell holds one digit, each unit holds every digit
+    Extended,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum AtMostOneKind {
+    /// A clause per pair of variables
+    Pairwise,
+    /// Sequential counter with auxiliary variables
+    Sequential,
+}
+
+#[derive(Args)]
+struct CnfArgs {
+    /// Puzzle as an 81 character string
+    #[clap(required_unless_present = "describe")]
+    puzzle: Option<String>,
+
+    /// Puzzle description with grid, variant and constraints, as for solve-variant
+    #[clap(short, long, conflicts_with = "puzzle")]
+    describe: Option<PathBuf>,
+
+    /// Variant rules for a puzzle given as a string
+    #[clap(long, conflicts_with = "describe")]
+    variant: Option<String>,
+
+    #[clap(short, long, value_enum, default_value_t = EncodingKind::Extended)]
+    encoding: EncodingKind,
+
+    /// How "at most one" is encoded
+    #[clap(short, long, value_enum, default_value_t = AtMostOneKind::Pairwise)]
+    at_most_one: AtMostOneKind,
+
+    /// Output file
+    #[clap(short, long)]
+    outfile: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatKind {
+    /// One 81 character puzzle per line
+    Line,
+    /// SadMan Sudoku single puzzle
+    Sdk,
+    /// SadMan Sudoku collection
+    Sdm,
+    /// Simple Sudoku grid
+    Ss,
+    /// Nine rows of nine cells
+    Grid,
+    /// HoDoKu pencil mark grid
+    Hodoku,
+}
+
+impl From<FormatKind> for Format {
+    fn from(kind: FormatKind) -> Self {
+        match kind {
+            FormatKind::Line => Format::Line,
+            FormatKind::Sdk => Format::Sdk,
+            FormatKind::Sdm => Format::Sdm,
+            FormatKind::Ss => Format::Ss,
+            FormatKind::Grid => Format::Grid,
+            FormatKind::Hodoku => Format::Hodoku,
+        }
+    }
 }
 
-fn main() -> io::Result<()> {
-    let args = Args::parse();
+#[derive(Args)]
+struct ConvertArgs {
+    /// Input file
+    #[clap(short, long)]
+    infile: PathBuf,
+
+    /// Output file
+    #[clap(short, long)]
+    outfile: Option<PathBuf>,
+
+    /// Input format; detected from the extension or contents by default
+    #[clap(short, long, value_enum)]
+    from: Option<FormatKind>,
+
+    /// Output format; taken from the output extension, or one puzzle per line
+    #[clap(short, long, value_enum)]
+    to: Option<FormatKind>,
+}
+
+#[cfg(feature = "serve")]
+#[derive(Args)]
+struct ServeArgs {
+    /// Port to listen on
+    #[clap(short, long, default_value_t = 8080)]
+    port: u16,
+
+    /// Address to listen on
+    #[clap(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Number of worker threads
+    #[clap(short = 't', long = "threads")]
+    num_threads: Option<usize>,
+
+    /// Give up on a puzzle after visiting this many search nodes
+    #[clap(long)]
+    max_nodes: Option<usize>,
+
+    /// Give up on a puzzle after this many milliseconds
+    #[clap(long)]
+    timeout_ms: Option<u64>,
+}
+
+fn parse_puzzle(puzzle: &str) -> oronsay::Result<Sudoku> {
+    let grid = puzzle.trim().as_bytes();
+    if grid.len() != 81 {
+        return Err(Error::Format(format!(
+            "puzzle is {} characters long, expected 81",
+            grid.len()
+        )));
+    }
+    Alphabet::default().validate(1, grid)?;
+    Ok(Puzzle::new(grid).sudoku())
+}
+
+/// The layout named by `--variant`, else by a header line of `infile`.
+fn variant_layout(variant: Option<&str>, infile: Option<&Path>) -> oronsay::Result<Layout> {
+    if let Some(variant) = variant {
+        return Layout::parse(variant);
+    }
+    let mut header = String::new();
+    if let Some(infile) = infile {
+        BufReader::new(File::open(infile)?).read_line(&mut header)?;
+    }
+    Layout::from_header(&header).unwrap_or_else(|| Ok(Layout::standard()))
+}
+
+fn solve(args: SolveArgs) -> oronsay::Result<()> {
+    let Some(infile) = args.infile else {
+        return Err(Error::Pipeline("no input file was given".into()));
+    };
+    let layout = variant_layout(args.variant.as_deref(), Some(&infile))?;
+    let mut alphabet = match args.permissive {
+        true => Alphabet::permissive(),
+        false => Alphabet::new(&args.empty)?,
+    };
+    if let Some(empty) = args.output_empty {
+        if !empty.is_ascii() {
+            return Err(Error::Format(format!("'{}' is not an ASCII character", empty)));
+        }
+        alphabet = alphabet.normalize_empty(empty as u8);
+    }
     let num_workers = args.num_threads.unwrap_or(get_num_threads());
-    let chunk_size = args.chunk_size * 1024;
+    let verbose = args.verbose;
+
+    let mut pipeline = Pipeline::new(SolverBasic::new(1, true).layout(layout))
+        .input_file(infile)
+        .threads(num_workers)
+        .chunk_size(args.chunk_size * 1024)
+        .hash(!args.no_hash)
+        .alphabet(alphabet)
+        .budget(Budget {
+            max_nodes: args.max_nodes,
+            timeout: args.timeout_ms.map(Duration::from_millis),
+        })
+        .on_chunk(move |id, stats| {
+            if verbose {
+                println!("Processed chunk ID: {}, {}", id, stats.puzzles);
+            }
+        });
+    if let Some(outfile) = args.outfile {
+        pipeline = pipeline.output_file(outfile);
+    }
+
+    let report = pipeline.run()?;
+    display_stats(&report);
+    Ok(())
+}
+
+fn solutions(args: SolutionsArgs) -> oronsay::Result<()> {
+    let sudoku = parse_puzzle(&args.puzzle)?;
+    let solver = SolverBasic::new(1, true).layout(variant_layout(args.variant.as_deref(), None)?);
+
+    let mut solutions = solver.solutions(&Puzzle::new(&sudoku.grid));
+    let mut found = 0;
+    for solution in solutions.by_ref().take(args.max) {
+        found += 1;
+        match args.pretty {
+            true => println!("{}", solution.pretty()),
+            false => println!("{}", solution),
+        }
+    }
+
+    // Only say the search stopped early if there is a solution past the limit
+    match solutions.next() {
+        Some(_) => eprintln!("Stopped after {} solutions", found),
+        None => eprintln!("Found {} solutions", found),
+    }
+    Ok(())
+}
+
+fn diff_solvers(args: DiffArgs) -> oronsay::Result<()> {
+    let puzzles = parse_puzzles(&fs::read(&args.infile)?)?;
+
+    // Look for two solutions so that counts can be compared
+    let mut harness = DiffHarness::new()
+        .threads(args.num_threads.unwrap_or(get_num_threads()))
+        .budget(Budget {
+            max_nodes: args.max_nodes,
+            timeout: None,
+        });
+    for kind in args.solvers {
+        let name = kind.to_string();
+        harness = match kind {
+            SolverKind::Basic => harness.solver(name, SolverBasic::new(2, true)),
+            SolverKind::BasicNoMcv => harness.solver(name, SolverBasic::new(2, false)),
+            SolverKind::Sat => harness.solver(name, find_sat_solver(2)?),
+        };
+    }
+
+    let report = harness.run(&puzzles);
+    match args.outfile {
+        Some(path) => {
+            fs::write(path, report.to_string())?;
+            println!(
+                "{} puzzles, {} disagreements",
+                report.puzzles,
+                report.disagreements.len()
+            );
+        }
+        None => print!("{}", report),
+    }
+    Ok(())
+}
+
+fn display_bench(result: &BenchResult, baseline: Option<&Baseline>) {
+    let change = baseline
+        .and_then(|baseline| baseline.change(result))
+        .map(|change| format!("{:+.1}%", change * 100.0))
+        .unwrap_or_default();
+    println!(
+        "{:<14} {:<10} {:>3} {:>12} ±{:>5.1}% {:>10.0} ±{:>5.1}% {:>9.2} {:>8}",
+        result.solver,
+        result.corpus,
+        result.threads,
+        (result.puzzles_per_sec.mean as u64).to_formatted_string(&Locale::en),
+        result.puzzles_per_sec.ci95 / result.puzzles_per_sec.mean * 100.0,
+        result.ns_per_puzzle.mean,
+        result.ns_per_puzzle.ci95 / result.ns_per_puzzle.mean * 100.0,
+        result.guesses_per_puzzle,
+        change
+    );
+}
+
+fn bench_solvers(args: BenchArgs) -> oronsay::Result<()> {
+    let corpora = args
+        .corpus
+        .iter()
+        .map(|name| Corpus::load(name))
+        .collect::<oronsay::Result<Vec<_>>>()?;
+    #[cfg(feature = "serde")]
+    let baseline = args.baseline.as_ref().map(Baseline::load).transpose()?;
+    #[cfg(not(feature = "serde"))]
+    let baseline = None;
+
+    let mut thread_counts = Vec::new();
+    if args.mode != BenchMode::Multi {
+        thread_counts.push(1);
+    }
+    if args.mode != BenchMode::Single {
+        thread_counts.push(args.num_threads.unwrap_or(get_num_threads()));
+    }
+
+    println!(
+        "{:<14} {:<10} {:>3} {:>20} {:>18} {:>9} {:>8}",
+        "solver", "corpus", "thr", "puzzles/s", "ns/puzzle", "guesses", "change"
+    );
+    let mut results = Baseline::default();
+    for kind in &args.solvers {
+        for corpus in &corpora {
+            for &threads in &thread_counts {
+                let config = BenchConfig {
+                    warmup: args.warmup,
+                    trials: args.trials,
+                    threads,
+                };
+                let name = kind.to_string();
+                let result = match kind {
+                    SolverKind::Basic => bench(&name, &SolverBasic::new(1, true), corpus, &config),
+                    SolverKind::BasicNoMcv => {
+                        bench(&name, &SolverBasic::new(1, false), corpus, &config)
+                    }
+                    SolverKind::Sat => bench(&name, &find_sat_solver(1)?, corpus, &config),
+                };
+                display_bench(&result, baseline.as_ref());
+                results.results.push(result);
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    if let Some(path) = args.save {
+        results.save(path)?;
+    }
+    Ok(())
+}
+
+fn dedup(args: DedupArgs) -> oronsay::Result<()> {
+    let input = BufReader::new(File::open(&args.infile)?);
+    let output: Box<dyn Write> = match args.outfile {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let alphabet = match args.permissive {
+        true => Alphabet::permissive(),
+        false => Alphabet::new(&args.empty)?,
+    };
+    let mut dedup = Dedup::new(args.num_threads.unwrap_or(get_num_threads())).alphabet(alphabet);
+    dedup.run(input, output)?;
+
+    if let Some(path) = args.report {
+        let mut report = BufWriter::new(File::create(path)?);
+        for class in dedup.classes().iter().filter(|class| class.count > 1) {
+            writeln!(report, "{} {} {}", class.count, class.canonical, class.first)?;
+        }
+        report.flush()?;
+    }
+
+    eprintln!(
+        "{} classes, {} duplicates dropped",
+        dedup.classes().len().to_formatted_string(&Locale::en),
+        dedup.duplicates().to_formatted_string(&Locale::en)
+    );
+    Ok(())
+}
+
+fn shuffle(args: ShuffleArgs) -> oronsay::Result<()> {
+    let puzzles = parse_puzzles(&fs::read(&args.infile)?)?;
+    let mut output: Box<dyn Write> = match args.outfile {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let mut rng = ChaCha8Rng::seed_from_u64(args.seed);
+    for puzzle in &puzzles {
+        for _ in 0..args.count {
+            let isomorph = Transform::random(&mut rng).apply(puzzle);
+            writeln!(output, "{}", isomorph)?;
+        }
+    }
+    output.flush()?;
+    Ok(())
+}
+
+fn minimize(args: MinimizeArgs) -> oronsay::Result<()> {
+    let puzzles = parse_puzzles(&fs::read(&args.infile)?)?;
+    let mut output: Box<dyn Write> = match args.outfile {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let results = minimize_batch(&puzzles, args.num_threads.unwrap_or(get_num_threads()));
+    let (mut minimal, mut not_unique, mut redundant) = (0, 0, 0);
+    for result in &results {
+        let minimal_puzzle = result.minimal.to_string();
+        match result.solutions {
+            0 => writeln!(output, "{},no solution", minimal_puzzle)?,
+            1 => writeln!(output, "{},{}", minimal_puzzle, result.redundant.len())?,
+            _ => writeln!(output, "{},multiple solutions", minimal_puzzle)?,
+        }
+        minimal += result.is_minimal() as usize;
+        not_unique += (result.solutions != 1) as usize;
+        redundant += result.redundant.len();
+    }
+    output.flush()?;
+
+    eprintln!(
+        "{} puzzles, {} already minimal, {} without a unique solution, {} redundant clues",
+        results.len(),
+        minimal,
+        not_unique,
+        redundant
+    );
+    Ok(())
+}
+
+/// Cell name in row/column notation, e.g. `r1c1` for cell 0.
+fn cell_name(cell: usize) -> String {
+    format!("r{}c{}", cell / 9 + 1, cell % 9 + 1)
+}
+
+fn cell_names(cells: &[usize]) -> String {
+    cells.iter().map(|&cell| cell_name(cell)).collect::<Vec<_>>().join(" ")
+}
+
+fn hint(args: HintArgs) -> oronsay::Result<()> {
+    let givens = parse_puzzle(&args.givens)?;
+    let current = match args.current {
+        Some(current) => parse_puzzle(&current)?,
+        None => givens,
+    };
+    let candidates = match args.candidates {
+        Some(candidates) => CandidateGrid::parse(&candidates)?,
+        None => CandidateGrid::from_sudoku(&current),
+    };
+    if args.pencil_marks {
+        print!("{}", candidates.pretty());
+    }
+    let solver = SolverBasic::new(2, true);
+
+    match oronsay::hint_with_candidates(&solver, &mut solver.make_state(), &givens, &current, &candidates) {
+        Hint::Step(step) => {
+            let action = match step.action {
+                Action::Place { cell, value } => format!("{} = {}", cell_name(cell), value),
+                Action::Eliminate(eliminations) => eliminations
+                    .iter()
+                    .map(|&(cell, value)| format!("{} <> {}", cell_name(cell), value))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            };
+            match step.because.is_empty() {
+                true => println!("{}: {}", step.technique, action),
+                false => println!("{}: {} (from {})", step.technique, action, cell_names(&step.because)),
+            }
+        }
+        Hint::Mistakes(cells) => println!("Mistakes: {}", cell_names(&cells)),
+        Hint::Solved => println!("Solved"),
+        Hint::NotUnique => return Err(Error::Format("puzzle does not have a unique solution".into())),
+    }
+    Ok(())
+}
+
+fn killer(args: KillerArgs) -> oronsay::Result<()> {
+    let layout = Killer::parse(&fs::read_to_string(&args.infile)?)?;
+    let givens = match args.givens {
+        Some(givens) => parse_puzzle(&givens)?,
+        None => Sudoku::new([b'.'; 81]),
+    };
+
+    // Look for two solutions to report whether the puzzle is unique
+    let solver = SolverKiller::new(layout, 2);
+    let Some(solution) = solver.solve(&Puzzle::new(&givens.grid), &mut solver.make_state()) else {
+        return Err(Error::Unsolvable {
+            line: 1,
+            puzzle: solver.killer().to_line(),
+        });
+    };
+    match args.pretty {
+        true => print!("{}", solver.killer().pretty(&solution.sudoku)),
+        false => println!("{}", solution.sudoku),
+    }
+    if solution.solutions > 1 {
+        eprintln!("Puzzle has more than one solution");
+    }
+    Ok(())
+}
 
-    let input_file = File::open(&args.infile)?;
-    let mmap = unsafe { MmapOptions::new().map(&input_file)? };
-    let mmap = Arc::new(mmap);
+fn solve_variant(args: SolveVariantArgs) -> oronsay::Result<()> {
+    let puzzle = VariantPuzzle::parse(&fs::read_to_string(&args.infile)?)?;
 
-    let (chunk_tx, chunk_rx) = channel::unbounded();
-    let (output_tx, output_rx) = channel::unbounded();
+    // Look for two solutions to report whether the puzzle is unique
+    let solver = puzzle.solver(2);
+    let Some(solution) = solver.solve(&Puzzle::new(&puzzle.grid.grid), &mut solver.make_state()) else {
+        return Err(Error::Unsolvable {
+            line: 1,
+            puzzle: puzzle.grid.to_string(),
+        });
+    };
+    match args.pretty {
+        true => print!("{}", solution.sudoku.pretty()),
+        false => println!("{}", solution.sudoku),
+    }
+    if solution.solutions > 1 {
+        eprintln!("Puzzle has more than one solution");
+    }
+    Ok(())
+}
 
-    let start = std::time::Instant::now();
+fn gattai(args: GattaiArgs) -> oronsay::Result<()> {
+    let puzzle = GattaiPuzzle::parse(Gattai::parse(&args.layout)?, &fs::read_to_string(&args.infile)?)?;
 
-    let solver = SolverBasic::new(1, true);
+    // Look for two solutions to report whether the puzzle is unique
+    let Some(solution) = SolverGattai::new(2).solve(&puzzle) else {
+        return Err(Error::Unsolvable {
+            line: 1,
+            puzzle: puzzle.to_lines().trim_end().replace('\n', " "),
+        });
+    };
+    match args.pretty {
+        true => print!("{}", solution.puzzle.pretty()),
+        false => print!("{}", solution.puzzle.to_lines()),
+    }
+    if solution.solutions > 1 {
+        eprintln!("Puzzle has more than one solution");
+    }
+    Ok(())
+}
 
-    let (line_length, reader_handle) =
-        Reader::spawn(Arc::clone(&mmap), chunk_tx, output_tx.clone(), chunk_size);
-    let worker_handles =
-        Worker::spawn_multiple(solver, line_length, chunk_rx, output_tx, num_workers);
-    let writer_handle = Writer::spawn(output_rx, args.outfile, args.no_hash, args.verbose);
+fn find_sat_solver(limit: usize) -> oronsay::Result<SolverSat> {
+    SolverSat::find(limit).ok_or_else(|| {
+        Error::Pipeline("no SAT solver found on PATH (kissat, cadical, cryptominisat5, minisat or glucose)".into())
+    })
+}
 
-    reader_handle.join().expect("Reader panicked");
-    for handle in worker_handles {
-        handle.join().expect("Worker panicked");
+fn cnf(args: CnfArgs) -> oronsay::Result<()> {
+    let puzzle = match (&args.describe, &args.puzzle) {
+        (Some(path), _) => VariantPuzzle::parse(&fs::read_to_string(path)?)?,
+        (None, Some(puzzle)) => VariantPuzzle {
+            grid: parse_puzzle(puzzle)?,
+            layout: variant_layout(args.variant.as_deref(), None)?,
+            constraints: Vec::new(),
+        },
+        (None, None) => return Err(Error::Format("no puzzle was given".into())),
+    };
+    let encoding = match args.encoding {
+        EncodingKind::Minimal => Encoding::Minimal,
+        EncodingKind::Extended => Encoding::Extended,
+    };
+    let at_most_one = match args.at_most_one {
+        AtMostOneKind::Pairwise => AtMostOne::Pairwise,
+        AtMostOneKind::Sequential => AtMostOne::Sequential,
+    };
+
+    let formula = puzzle
+        .encoder(encoding)
+        .at_most_one(at_most_one)
+        .encode(&Puzzle::new(&puzzle.grid.grid));
+    let output: Box<dyn Write> = match args.outfile {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    formula.write_dimacs(output)?;
+    Ok(())
+}
+
+fn convert(args: ConvertArgs) -> oronsay::Result<()> {
+    let text = fs::read_to_string(&args.infile)?;
+    let from = match args.from {
+        Some(kind) => kind.into(),
+        None => Format::detect_file(&args.infile, &text),
+    };
+    let to = match (args.to, &args.outfile) {
+        (Some(kind), _) => kind.into(),
+        (None, Some(path)) => Format::from_extension(path).unwrap_or(Format::Line),
+        (None, None) => Format::Line,
+    };
+
+    let records = from.parse(&text)?;
+    let output: Box<dyn Write> = match args.outfile {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    to.write(&records, output)?;
+    eprintln!("Converted {} puzzles from {} to {}", records.len(), from, to);
+    Ok(())
+}
+
+#[cfg(feature = "serve")]
+fn serve(args: ServeArgs) -> oronsay::Result<()> {
+    // Two solutions, so /unique and /rate can tell a unique puzzle apart
+    let mut service = Service::new(SolverBasic::new(2, true)).budget(Budget {
+        max_nodes: args.max_nodes,
+        timeout: args.timeout_ms.map(Duration::from_millis),
+    });
+    if let Some(num_threads) = args.num_threads {
+        service = service.threads(num_threads);
+    }
+    let handle = service.bind((args.host.as_str(), args.port))?;
+    eprintln!("Listening on http://{}", handle.addr());
+    handle.join()
+}
+
+fn run(cli: Cli) -> oronsay::Result<()> {
+    match cli.command {
+        Some(Command::Solutions(args)) => solutions(args),
+        Some(Command::DiffSolvers(args)) => diff_solvers(args),
+        Some(Command::Bench(args)) => bench_solvers(args),
+        Some(Command::Dedup(args)) => dedup(args),
+        Some(Command::Shuffle(args)) => shuffle(args),
+        Some(Command::Minimize(args)) => minimize(args),
+        Some(Command::Hint(args)) => hint(args),
+        Some(Command::Killer(args)) => killer(args),
+        Some(Command::SolveVariant(args)) => solve_variant(args),
+        Some(Command::Gattai(args)) => gattai(args),
+        Some(Command::Cnf(args)) => cnf(args),
+        Some(Command::Convert(args)) => convert(args),
+        #[cfg(feature = "serve")]
+        Some(Command::Serve(args)) => serve(args),
+        None => solve(cli.solve),
     }
-    let (hash, stats) = writer_handle.join().expect("Writer panicked");
+}
 
-    display_stats(&stats, hash, start.elapsed(), num_workers)
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,178 @@
+use crate::{
+    batch::map_chunks,
+    solver::{Solver, SolverBasic},
+    sudoku::{Puzzle, Sudoku},
+};
+
+pub struct MinimizeResult {
+    /// Solutions of the input, counted up to two.
+    pub solutions: usize,
+    /// Clues of the input that could each be removed on their own without losing uniqueness.
+    pub redundant: Vec<usize>,
+    /// The input with redundant clues stripped until every remaining one is
+    /// necessary; the input itself if it does not have a unique solution.
+    pub minimal: Sudoku,
+}
+
+impl MinimizeResult {
+    pub fn is_minimal(&self) -> bool {
+        self.solutions == 1 && self.redundant.is_empty()
+    }
+}
+
+fn is_clue(value: u8) -> bool {
+    (b'1'..=b'9').contains(&value)
+}
+
+/// Checks which clues of each puzzle are redundant and strips them.
+///
+/// Every (puzzle, clue) removal is checked in parallel first, then each
+/// uniquely solvable puzzle is reduced in a single greedy pass over its
+/// redundant clues. One pass is enough: a clue that is needed stays needed
+/// when other clues are removed, since that only adds solutions.
+pub fn minimize_batch(puzzles: &[Sudoku], num_threads: usize) -> Vec<MinimizeResult> {
+    let solver = SolverBasic::new(2, true);
+    let count = |sudoku: &Sudoku, state: &mut _| {
+        solver
+            .solve(&Puzzle::new(&sudoku.grid), state)
+            .map_or(0, |solution| solution.solutions)
+    };
+
+    let solutions = map_chunks(
+        puzzles,
+        num_threads,
+        || solver.make_state(),
+        |chunk, state| chunk.iter().map(|sudoku| count(sudoku, state)).collect::<Vec<_>>(),
+    )
+    .concat();
+
+    let removals = puzzles
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| solutions[*index] == 1)
+        .flat_map(|(index, sudoku)| {
+            (0..sudoku.grid.len())
+                .filter(|&cell| is_clue(sudoku.grid[cell]))
+                .map(move |cell| (index, cell))
+        })
+        .collect::<Vec<_>>();
+    let redundant = map_chunks(
+        &removals,
+        num_threads,
+        || solver.make_state(),
+        |chunk, state| {
+            chunk
+                .iter()
+                .map(|&(index, cell)| {
+                    let mut reduced = puzzles[index];
+                    reduced.grid[cell] = b'.';
+                    count(&reduced, state) == 1
+                })
+                .collect::<Vec<_>>()
+        },
+    )
+    .concat();
+
+    let mut results = puzzles
+        .iter()
+        .zip(solutions)
+        .map(|(sudoku, solutions)| MinimizeResult {
+            solutions,
+            redundant: Vec::new(),
+            minimal: *sudoku,
+        })
+        .collect::<Vec<_>>();
+    for (&(index, cell), redundant) in removals.iter().zip(redundant) {
+        if redundant {
+            results[index].redundant.push(cell);
+        }
+    }
+
+    let minimal = map_chunks(
+        &results,
+        num_threads,
+        || solver.make_state(),
+        |chunk, state| {
+            chunk
+                .iter()
+                .map(|result| {
+                    let mut minimal = result.minimal;
+                    for &cell in &result.redundant {
+                        let clue = minimal.grid[cell];
+                        minimal.grid[cell] = b'.';
+                        if count(&minimal, state) != 1 {
+                            minimal.grid[cell] = clue;
+                        }
+                    }
+                    minimal
+                })
+                .collect::<Vec<_>>()
+        },
+    )
+    .concat();
+    for (result, minimal) in results.iter_mut().zip(minimal) {
+        result.minimal = minimal;
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &[u8] = b"53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+    const SOLUTION: &[u8] = b"534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+    fn sudoku(grid: &[u8]) -> Sudoku {
+        Sudoku::new(grid.try_into().unwrap())
+    }
+
+    fn solutions(sudoku: &Sudoku) -> usize {
+        let solver = SolverBasic::new(2, true);
+        solver
+            .solve(&Puzzle::new(&sudoku.grid), &mut solver.make_state())
+            .map_or(0, |solution| solution.solutions)
+    }
+
+    #[test]
+    fn minimal_puzzles_are_unique_and_need_every_clue() {
+        // Extra clues from the solution make the puzzle further from minimal
+        let mut padded = sudoku(PUZZLE);
+        for cell in (0..81).step_by(7) {
+            padded.grid[cell] = SOLUTION[cell];
+        }
+        let results = minimize_batch(&[sudoku(PUZZLE), padded], 2);
+
+        for (result, input) in results.iter().zip([sudoku(PUZZLE), padded]) {
+            assert_eq!(result.solutions, 1);
+            assert!(!result.redundant.is_empty() && !result.is_minimal());
+            let minimal = result.minimal;
+            assert_eq!(solutions(&minimal), 1);
+            for cell in 0..81 {
+                if is_clue(minimal.grid[cell]) {
+                    assert_eq!(minimal.grid[cell], input.grid[cell]);
+                    let mut reduced = minimal;
+                    reduced.grid[cell] = b'.';
+                    assert_eq!(solutions(&reduced), 2, "clue {} of {} is redundant", cell, minimal);
+                }
+            }
+            let again = minimize_batch(&[minimal], 1);
+            assert!(again[0].is_minimal());
+            assert_eq!(again[0].minimal, minimal);
+        }
+    }
+
+    #[test]
+    fn puzzles_without_a_unique_solution_are_kept() {
+        let mut open = sudoku(PUZZLE);
+        open.grid[..9].fill(b'.');
+        let broken = sudoku(&[b"55", &PUZZLE[2..]].concat());
+        let results = minimize_batch(&[open, broken], 3);
+        assert_eq!(results.iter().map(|result| result.solutions).collect::<Vec<_>>(), [2, 0]);
+        for (result, input) in results.iter().zip([open, broken]) {
+            assert!(result.redundant.is_empty() && !result.is_minimal());
+            assert_eq!(result.minimal, input);
+        }
+        assert!(minimize_batch(&[], 2).is_empty());
+    }
+}
@@ -0,0 +1,382 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::channel;
+use memmap2::MmapOptions;
+
+use crate::{
+    alphabet::Alphabet,
+    error::{Error, Result},
+    reader::Reader,
+    solver::{Budget, Solver},
+    types::{CancelToken, ChunkStats, Source},
+    worker::Worker,
+    writer::{ChunkCallback, Writer},
+};
+
+enum Input {
+    File(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+enum Output {
+    None,
+    File(PathBuf),
+    Writer(Box<dyn Write + Send>),
+}
+
+/// Summary of a finished pipeline run.
+pub struct RunReport {
+    pub stats: ChunkStats,
+    pub hash: Option<String>,
+    pub elapsed: Duration,
+    pub threads: usize,
+}
+
+/// Snapshot of how much of the input has been written so far.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Progress {
+    pub chunks: usize,
+    pub puzzles: usize,
+}
+
+#[derive(Default)]
+struct Counters {
+    chunks: AtomicUsize,
+    puzzles: AtomicUsize,
+}
+
+/// Builder for a reader -> workers -> writer run over a puzzle file or buffer.
+pub struct Pipeline<S: Solver> {
+    solver: S,
+    input: Option<Input>,
+    output: Output,
+    num_threads: usize,
+    chunk_size: usize,
+    hash: bool,
+    budget: Budget,
+    alphabet: Alphabet,
+    on_chunk: Option<ChunkCallback>,
+}
+
+impl<S: Solver> Pipeline<S> {
+    pub fn new(solver: S) -> Self {
+        Self {
+            solver,
+            input: None,
+            output: Output::None,
+            num_threads: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            chunk_size: 16 * 1024,
+            hash: true,
+            budget: Budget::unlimited(),
+            alphabet: Alphabet::default(),
+            on_chunk: None,
+        }
+    }
+
+    /// File of puzzles, one per line with an optional header.
+    pub fn input_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.input = Some(Input::File(path.into()));
+        self
+    }
+
+    /// Puzzles already in memory, in the same layout as an input file.
+    pub fn input_bytes(mut self, bytes: impl Into<Vec<u8>>) -> Self {
+        self.input = Some(Input::Bytes(bytes.into()));
+        self
+    }
+
+    /// Write solved lines to `path`, truncating it first.
+    pub fn output_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.output = Output::File(path.into());
+        self
+    }
+
+    /// Write solved lines to an arbitrary sink.
+    pub fn output_writer(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.output = Output::Writer(Box::new(writer));
+        self
+    }
+
+    pub fn threads(mut self, num_threads: usize) -> Self {
+        self.num_threads = num_threads.max(1);
+        self
+    }
+
+    /// Chunk size in bytes, rounded down to a whole number of lines but never
+    /// less than one.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Whether to compute a SHA-256 hash of the output.
+    pub fn hash(mut self, hash: bool) -> Self {
+        self.hash = hash;
+        self
+    }
+
+    /// Per-puzzle limits; puzzles that exceed them are counted in `ChunkStats::gave_up`.
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Which bytes mean an empty cell; any other non-digit stops the run with
+    /// `Error::InvalidCharacter`. Defaults to `.` and `0`.
+    pub fn alphabet(mut self, alphabet: Alphabet) -> Self {
+        self.alphabet = alphabet;
+        self
+    }
+
+    /// Called from the writer thread after each chunk, in input order.
+    pub fn on_chunk(mut self, on_chunk: impl FnMut(usize, &ChunkStats) + Send + 'static) -> Self {
+        self.on_chunk = Some(Box::new(on_chunk));
+        self
+    }
+
+    /// Starts the run in the background.
+    pub fn spawn(self) -> Result<PipelineHandle> {
+        let start = Instant::now();
+        let source = match self.input {
+            Some(Input::File(path)) => {
+                let input_file = File::open(path)?;
+                Source::Mmap(unsafe { MmapOptions::new().map(&input_file)? })
+            }
+            Some(Input::Bytes(bytes)) => Source::Bytes(bytes),
+            None => return Err(Error::Pipeline("no input was given".into())),
+        };
+
+        let sink: Option<Box<dyn Write + Send>> = match self.output {
+            Output::None => None,
+            Output::File(path) => {
+                let file = OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(path)?;
+                Some(Box::new(BufWriter::new(file)))
+            }
+            Output::Writer(writer) => Some(writer),
+        };
+
+        let counters = Arc::new(Counters::default());
+        let on_chunk = {
+            let counters = Arc::clone(&counters);
+            let mut user = self.on_chunk;
+            Box::new(move |id: usize, stats: &ChunkStats| {
+                counters.chunks.store(stats.chunks, Ordering::Relaxed);
+                counters.puzzles.store(stats.puzzles, Ordering::Relaxed);
+                if let Some(user) = user.as_mut() {
+                    user(id, stats);
+                }
+            })
+        };
+
+        let cancel = CancelToken::new();
+        let (chunk_tx, chunk_rx) = channel::unbounded();
+        let (output_tx, output_rx) = channel::unbounded();
+
+        let (line_length, reader) = Reader::spawn(
+            Arc::new(source),
+            chunk_tx,
+            output_tx.clone(),
+            self.chunk_size,
+            cancel.clone(),
+        )?;
+        let workers = Worker::spawn_multiple(
+            self.solver,
+            line_length,
+            chunk_rx,
+            output_tx,
+            cancel.clone(),
+            self.budget,
+            self.alphabet,
+            self.num_threads,
+        );
+        let writer = Writer::spawn(output_rx, sink, !self.hash, Some(on_chunk));
+
+        Ok(PipelineHandle {
+            reader,
+            workers,
+            writer,
+            cancel,
+            counters,
+            start,
+            threads: self.num_threads,
+        })
+    }
+
+    /// Runs to completion on the calling thread.
+    pub fn run(self) -> Result<RunReport> {
+        self.spawn()?.join()
+    }
+}
+
+/// A running pipeline.
+pub struct PipelineHandle {
+    reader: thread::JoinHandle<()>,
+    workers: Vec<thread::JoinHandle<Result<()>>>,
+    writer: thread::JoinHandle<Result<(Option<String>, ChunkStats)>>,
+    cancel: CancelToken,
+    counters: Arc<Counters>,
+    start: Instant,
+    threads: usize,
+}
+
+impl PipelineHandle {
+    pub fn progress(&self) -> Progress {
+        Progress {
+            chunks: self.counters.chunks.load(Ordering::Relaxed),
+            puzzles: self.counters.puzzles.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Asks the reader and workers to stop; `join` then returns `Error::Cancelled`.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.writer.is_finished()
+    }
+
+    pub fn join(self) -> Result<RunReport> {
+        let panicked = |stage: &str| Error::Pipeline(format!("{} thread panicked", stage));
+
+        // Join everything before reporting so no thread outlives the handle
+        let reader = self.reader.join().map_err(|_| panicked("reader"));
+        let workers = self
+            .workers
+            .into_iter()
+            .map(|worker| worker.join().map_err(|_| panicked("worker"))?)
+            .collect::<Vec<_>>();
+        let writer = self.writer.join().map_err(|_| panicked("writer"))?;
+
+        reader?;
+        for worker in workers {
+            worker?;
+        }
+        let (hash, stats) = writer?;
+
+        if self.cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        Ok(RunReport {
+            stats,
+            hash,
+            elapsed: self.start.elapsed(),
+            threads: self.threads,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solver::SolverBasic, sudoku::N_CELLS};
+
+    const EASY: &[u8] = include_bytes!("../corpora/easy.txt");
+
+    #[test]
+    fn chunk_size_below_one_line_reads_a_line_at_a_time() {
+        for chunk_size in [0, 1, 81, 83] {
+            let report = Pipeline::new(SolverBasic::new(1, true))
+                .input_bytes(EASY)
+                .threads(2)
+                .chunk_size(chunk_size)
+                .hash(false)
+                .run()
+                .unwrap();
+            assert_eq!(report.stats.puzzles, 500, "chunk size {}", chunk_size);
+            assert_eq!(report.stats.solutions, 500, "chunk size {}", chunk_size);
+        }
+    }
+
+    /// `EASY` with line `line` (1-based) changed by `edit`.
+    fn easy_with(line: usize, edit: impl FnOnce(&mut [u8])) -> Vec<u8> {
+        let mut input = EASY.to_vec();
+        let start = (line - 1) * (N_CELLS + 1);
+        edit(&mut input[start..start + N_CELLS]);
+        input
+    }
+
+    #[test]
+    fn unsolvable_puzzles_stop_the_run_at_their_line() {
+        // Repeat a digit in the first row of line 300
+        let input = easy_with(300, |grid| {
+            let given = (0..9).find(|&col| grid[col] != b'.').unwrap();
+            let empty = (0..9).find(|&col| grid[col] == b'.').unwrap();
+            grid[empty] = grid[given];
+        });
+        for threads in [1, 4] {
+            let written = Arc::new(AtomicUsize::new(0));
+            let counter = Arc::clone(&written);
+            let result = Pipeline::new(SolverBasic::new(1, true))
+                .input_bytes(input.clone())
+                .threads(threads)
+                .chunk_size(N_CELLS + 1)
+                .on_chunk(move |_, stats| counter.store(stats.puzzles, Ordering::Relaxed))
+                .run();
+            match result {
+                Err(Error::Unsolvable { line, puzzle }) => {
+                    assert_eq!(line, 300);
+                    assert_eq!(puzzle.as_bytes(), &input[299 * (N_CELLS + 1)..][..N_CELLS]);
+                }
+                other => panic!("expected an unsolvable puzzle, got {:?}", other.err()),
+            }
+            // Output stops in front of the failing line
+            assert!(written.load(Ordering::Relaxed) < 300, "{} threads", threads);
+        }
+    }
+
+    #[test]
+    fn invalid_characters_are_reported_at_their_line_and_column() {
+        let input = easy_with(123, |grid| grid[16] = b'x');
+        let run = |input: Vec<u8>| {
+            Pipeline::new(SolverBasic::new(1, true))
+                .input_bytes(input)
+                .threads(3)
+                .chunk_size(1000)
+                .hash(false)
+                .run()
+        };
+        let position = |result: Result<RunReport>| match result {
+            Err(Error::InvalidCharacter { line, column, byte }) => (line, column, byte),
+            other => panic!("expected an invalid character, got {:?}", other.err()),
+        };
+        assert_eq!(position(run(input.clone())), (123, 17, b'x'));
+        // A header is the first line
+        assert_eq!(position(run([b"puzzles\n", &input[..]].concat())), (124, 17, b'x'));
+        assert!(Pipeline::new(SolverBasic::new(1, true))
+            .input_bytes(input)
+            .alphabet(Alphabet::permissive())
+            .run()
+            .is_ok());
+    }
+
+    #[test]
+    fn cancelled_runs_report_cancellation() {
+        let input = EASY.repeat(20);
+        let handle = Pipeline::new(SolverBasic::new(1, true))
+            .input_bytes(input)
+            .threads(2)
+            .chunk_size(N_CELLS + 1)
+            .spawn()
+            .unwrap();
+        handle.cancel();
+        assert!(matches!(handle.join(), Err(Error::Cancelled)));
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use pyo3::{
+    exceptions::{PyIOError, PyValueError},
+    prelude::*,
+    types::PyBytes,
+};
+
+use crate::{
+    batch::solve_batch,
+    error::Error,
+    hint::rate as rate_puzzle,
+    pipeline::Pipeline,
+    solver::{Solver, SolverBasic},
+    sudoku::{Puzzle, Sudoku, N_CELLS},
+};
+
+impl From<Error> for PyErr {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => PyIOError::new_err(err.to_string()),
+            err => PyValueError::new_err(err.to_string()),
+        }
+    }
+}
+
+/// Collects pipeline output for `solve_file`.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn default_threads(threads: Option<usize>) -> usize {
+    threads.unwrap_or_else(|| thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+}
+
+/// The first solution of `puzzle`, or `None` if it has none. The puzzle may
+/// be in any format `oronsay convert` reads.
+#[pyfunction]
+fn solve(py: Python<'_>, puzzle: &str) -> PyResult<Option<String>> {
+    let sudoku = Sudoku::from_str(puzzle)?;
+    Ok(py.detach(|| {
+        let solver = SolverBasic::new(1, true);
+        solver
+            .solve(&Puzzle::new(&sudoku.grid), &mut solver.make_state())
+            .map(|solution| solution.sudoku.to_string())
+    }))
+}
+
+/// Number of solutions of `puzzle`, counting no further than `limit`.
+#[pyfunction]
+#[pyo3(signature = (puzzle, limit = 2))]
+fn count_solutions(py: Python<'_>, puzzle: &str, limit: usize) -> PyResult<usize> {
+    let sudoku = Sudoku::from_str(puzzle)?;
+    Ok(py.detach(|| {
+        let solver = SolverBasic::new(limit.max(1), true);
+        solver
+            .solve(&Puzzle::new(&sudoku.grid), &mut solver.make_state())
+            .map_or(0, |solution| solution.solutions)
+    }))
+}
+
+/// The hardest technique needed to solve `puzzle` by hand and the number of
+/// steps taken, or `None` if it does not have a unique solution.
+#[pyfunction]
+fn rate(py: Python<'_>, puzzle: &str) -> PyResult<Option<(&'static str, usize)>> {
+    let sudoku = Sudoku::from_str(puzzle)?;
+    Ok(py.detach(|| {
+        let solver = SolverBasic::new(2, true);
+        rate_puzzle(&solver, &mut solver.make_state(), &sudoku).map(|rating| (rating.hardest.name(), rating.steps))
+    }))
+}
+
+/// The smallest grid equivalent to `puzzle` under the Sudoku symmetries.
+#[pyfunction]
+fn canonicalize(py: Python<'_>, puzzle: &str) -> PyResult<String> {
+    let sudoku = Sudoku::from_str(puzzle)?;
+    Ok(py.detach(|| sudoku.canonical().to_string()))
+}
+
+/// Solves each puzzle on `threads` threads, by default one per core, and
+/// returns the solutions in order, `None` for a puzzle without one.
+#[pyfunction]
+#[pyo3(signature = (puzzles, threads = None))]
+fn solve_many(py: Python<'_>, puzzles: Vec<String>, threads: Option<usize>) -> PyResult<Vec<Option<String>>> {
+    let sudokus = puzzles
+        .iter()
+        .map(|puzzle| Sudoku::from_str(puzzle))
+        .collect::<Result<Vec<_>, _>>()?;
+    let report = py.detach(|| solve_batch(&SolverBasic::new(1, true), &sudokus, default_threads(threads)));
+    Ok(report
+        .solutions
+        .into_iter()
+        .map(|solution| solution.map(|solution| solution.sudoku.to_string()))
+        .collect())
+}
+
+/// Solves a puzzle file, one puzzle per line, like `oronsay -i`. Returns the
+/// solutions packed as 81 ASCII digits each, in input order, ready for
+/// `numpy.frombuffer(solutions, dtype=numpy.uint8).reshape(-1, 81) - ord("0")`.
+/// Raises `ValueError` if a puzzle has no solution.
+#[pyfunction]
+#[pyo3(signature = (path, threads = None))]
+fn solve_file(py: Python<'_>, path: PathBuf, threads: Option<usize>) -> PyResult<Py<PyBytes>> {
+    let output = SharedBuffer::default();
+    let report = py.detach(|| {
+        Pipeline::new(SolverBasic::new(1, true))
+            .input_file(path)
+            .output_writer(output.clone())
+            .threads(default_threads(threads))
+            .hash(false)
+            .run()
+    })?;
+
+    let solutions = packed_solutions(&output.0.lock().unwrap(), report.stats.puzzles)?;
+    Ok(PyBytes::new(py, &solutions).unbind())
+}
+
+/// The solutions in pipeline `output` for `puzzles` puzzles, packed back to
+/// back. Output lines are "puzzle,solution", after the header line passed
+/// through as is if the input had one.
+fn packed_solutions(output: &[u8], puzzles: usize) -> Result<Vec<u8>, Error> {
+    let lines = output
+        .split(|&byte| byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    let mismatch = || Error::Pipeline(format!("output has {} lines for {} puzzles", lines.len(), puzzles));
+    if lines.len() < puzzles || lines.len() > puzzles + 1 {
+        return Err(mismatch());
+    }
+
+    let mut solutions = Vec::with_capacity(puzzles * N_CELLS);
+    for line in &lines[lines.len() - puzzles..] {
+        if line.len() != 2 * N_CELLS + 1 || line[N_CELLS] != b',' {
+            return Err(Error::Pipeline(format!(
+                "output line '{}' is not a puzzle and its solution",
+                String::from_utf8_lossy(line)
+            )));
+        }
+        solutions.extend_from_slice(&line[N_CELLS + 1..]);
+    }
+    Ok(solutions)
+}
+
+#[pymodule]
+fn oronsay(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(solve, module)?)?;
+    module.add_function(wrap_pyfunction!(count_solutions, module)?)?;
+    module.add_function(wrap_pyfunction!(rate, module)?)?;
+    module.add_function(wrap_pyfunction!(canonicalize, module)?)?;
+    module.add_function(wrap_pyfunction!(solve_many, module)?)?;
+    module.add_function(wrap_pyfunction!(solve_file, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &str = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+    const SOLUTION: &str = "534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+    fn message(result: Result<Vec<u8>, Error>) -> String {
+        match result {
+            Err(Error::Pipeline(message)) => message,
+            other => panic!("expected a pipeline error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn packed_solutions_skip_the_header_only() {
+        let line = format!("{},{}\n", PUZZLE, SOLUTION);
+        let output = line.repeat(3);
+        assert_eq!(packed_solutions(output.as_bytes(), 3).unwrap(), SOLUTION.repeat(3).as_bytes());
+
+        // A header as long as a solution line is still only a header
+        let header = format!("{},{}\n", "p".repeat(N_CELLS), "s".repeat(N_CELLS));
+        let with_header = header + &output;
+        assert_eq!(packed_solutions(with_header.as_bytes(), 3).unwrap(), SOLUTION.repeat(3).as_bytes());
+        assert_eq!(packed_solutions(b"", 0).unwrap(), b"");
+    }
+
+    #[test]
+    fn packed_solutions_reject_output_that_does_not_match_the_puzzles() {
+        let line = format!("{},{}\n", PUZZLE, SOLUTION);
+        assert!(message(packed_solutions(line.repeat(2).as_bytes(), 3)).contains("2 lines for 3 puzzles"));
+        assert!(message(packed_solutions(line.repeat(3).as_bytes(), 1)).contains("3 lines for 1 puzzles"));
+        let short = format!("{}\n{},{}\n", line.trim_end(), PUZZLE, &SOLUTION[1..]);
+        assert!(message(packed_solutions(short.as_bytes(), 2)).contains("is not a puzzle and its solution"));
+    }
+}
@@ -1,9 +1,11 @@
 use std::{sync::Arc, thread};
 
 use crossbeam::channel;
-use memmap2::Mmap;
-
-use crate::types::{ChunkStats, PuzzleChunk, SolvedChunk};
+use crate::{
+    error::{Error, Result},
+    sudoku::N_CELLS,
+    types::{CancelToken, ChunkStats, PuzzleChunk, SolvedChunk, Source},
+};
 
 struct ReaderMetadata {
     line_length: usize,
@@ -13,28 +15,34 @@ struct ReaderMetadata {
 
 pub struct Reader;
 
+/// Whether `first`, the first line of an input, is a header rather than a
+/// puzzle. Puzzle lines all have the same length, so a first line that is
+/// not as long as the `second` is taken for a header. Neither line includes
+/// its `\n`.
+pub(crate) fn is_header(first: &[u8], second: &[u8]) -> bool {
+    first.len() != second.len()
+}
+
 impl Reader {
     pub fn spawn(
-        mmap: Arc<Mmap>,
+        source: Arc<Source>,
         chunk_tx: channel::Sender<PuzzleChunk>,
         output_tx: channel::Sender<SolvedChunk>,
         chunk_size: usize,
-    ) -> (usize, thread::JoinHandle<()>) {
-        let metadata = Self::analyze_mmap(&mmap);
-
+        cancel: CancelToken,
+    ) -> Result<(usize, thread::JoinHandle<()>)> {
         let ReaderMetadata {
             line_length,
             header_text,
             data_start,
-        } = match metadata {
-            Some(metadata) => metadata,
-            None => panic!("Failed to analyze mmap"),
-        };
+        } = Self::analyze(&source)?;
 
-        let chunk_size = chunk_size - (chunk_size % line_length);
+        // Whole lines only, and at least one so the reader always moves on
+        let chunk_size = (chunk_size - chunk_size % line_length).max(line_length);
 
         // Optionally send header
         let mut next_id = 0;
+        let mut first_line = 1;
         if let Some(header_text) = header_text {
             output_tx
                 .send(SolvedChunk {
@@ -42,51 +50,62 @@ impl Reader {
                     data: header_text,
                     stats: ChunkStats::default(),
                 })
-                .expect("Failed to send header chunk");
+                .map_err(|_| Error::Pipeline("writer hung up before the header".into()))?;
             next_id += 1;
+            first_line += 1;
         }
 
         let reader = thread::spawn(move || {
             let mut start = data_start;
 
-            while start < mmap.len() {
-                let end = (start + chunk_size).min(mmap.len());
+            while start < source.len() && !cancel.is_cancelled() {
+                let end = (start + chunk_size).min(source.len());
                 let chunk = PuzzleChunk {
                     id: next_id,
+                    first_line: first_line + (start - data_start) / line_length,
                     start,
                     end,
-                    mmap: Arc::clone(&mmap),
+                    source: Arc::clone(&source),
                 };
-                chunk_tx.send(chunk).expect("Failed to send chunk");
+                // Workers only hang up early when the run has been stopped
+                if chunk_tx.send(chunk).is_err() {
+                    break;
+                }
                 next_id += 1;
                 start += chunk_size;
             }
         });
 
-        (line_length, reader)
+        Ok((line_length, reader))
     }
 
     /// Finds line metadata and extracts header (if present)
-    fn analyze_mmap(mmap: &Mmap) -> Option<ReaderMetadata> {
-        let (first, second) = Self::find_line_bounds(&mmap[..])?;
+    fn analyze(data: &[u8]) -> Result<ReaderMetadata> {
+        let (first, second) = Self::find_line_bounds(data)
+            .ok_or_else(|| Error::Format("expected at least two lines".into()))?;
         let line_length = second - first;
+        if line_length <= N_CELLS {
+            return Err(Error::Format(format!(
+                "lines are {} bytes long, expected at least {}",
+                line_length,
+                N_CELLS + 1
+            )));
+        }
+        if data[..first].iter().all(|&b| b == b'\r') {
+            return Err(Error::Format("first line is empty".into()));
+        }
 
-        // Determine if there's a header by looking at pre/post fix newline formatting
-        let (pre_fix, post_fix) = if first > 0 && mmap[first - 1] == b'\r' {
-            (first - 2, first + 2)
-        } else {
-            (first - 1, first + 1)
-        };
-
-        let (header_text, data_start) = if post_fix != line_length {
-            let mut header = mmap[..=pre_fix].iter().copied().collect::<Vec<u8>>();
-            header.push(b'\n');
-            (Some(header), post_fix)
-        } else {
-            (None, 0)
+        let (header_text, data_start) = match is_header(&data[..first], &data[first + 1..second]) {
+            true => {
+                let header = &data[..first];
+                let mut header = header.strip_suffix(b"\r").unwrap_or(header).to_vec();
+                header.push(b'\n');
+                (Some(header), first + 1)
+            }
+            false => (None, 0),
         };
 
-        Some(ReaderMetadata {
+        Ok(ReaderMetadata {
             line_length,
             header_text,
             data_start,
@@ -0,0 +1,441 @@
+use alloc::{collections::BTreeSet, format, string::String, sync::Arc, vec, vec::Vec};
+use core::fmt::Write;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::{
+    candidates::{bit, col_cell, row_cell, value_of},
+    constraint::Constraint,
+    error::{Error, Result},
+    layout::Layout,
+    sudoku::{Puzzle, Sudoku, N_CELLS},
+};
+
+/// Variables `1..=729` say that a cell holds a digit; any others are auxiliary.
+const CELL_VARS: usize = N_CELLS * 9;
+
+/// Which clauses describe the rules, after Kwon and Jain's Sudoku encodings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// Every cell holds a digit and no unit holds a digit twice.
+    Minimal,
+    /// The minimal clauses, plus every cell holds at most one digit and every
+    /// unit holds every digit. Redundant, but usually easier for a solver.
+    Extended,
+}
+
+/// How "at most one of these is true" is written as clauses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AtMostOne {
+    /// A binary clause for every pair, with no auxiliary variables.
+    Pairwise,
+    /// Sinz's sequential counter: linear in size, with auxiliary variables.
+    Sequential,
+}
+
+/// The variable for `value` (`1..=9`) in `cell`, `9 * cell + value` with
+/// cells numbered from zero in reading order.
+pub fn var(cell: usize, value: u8) -> i32 {
+    (cell * 9 + value as usize) as i32
+}
+
+/// A formula in conjunctive normal form, clauses as DIMACS literals.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cnf {
+    pub num_vars: usize,
+    pub clauses: Vec<Vec<i32>>,
+}
+
+impl Cnf {
+    pub fn add_clause(&mut self, clause: Vec<i32>) {
+        self.clauses.push(clause);
+    }
+
+    /// Adds a unit clause for each given of `puzzle`.
+    pub fn add_givens(&mut self, puzzle: &Puzzle) {
+        for (cell, &given) in puzzle.grid.iter().enumerate() {
+            if let Some(value) = value_of(given) {
+                self.add_clause(vec![var(cell, value)]);
+            }
+        }
+    }
+
+    fn new_var(&mut self) -> i32 {
+        self.num_vars += 1;
+        self.num_vars as i32
+    }
+
+    fn at_most_one(&mut self, vars: &[i32], encoding: AtMostOne) {
+        match encoding {
+            AtMostOne::Pairwise => {
+                for (i, &a) in vars.iter().enumerate() {
+                    for &b in &vars[i + 1..] {
+                        self.add_clause(vec![-a, -b]);
+                    }
+                }
+            }
+            AtMostOne::Sequential => {
+                let Some((&last, rest)) = vars.split_last() else {
+                    return;
+                };
+                // `seen` is true once any of the variables so far is
+                let mut seen: Option<i32> = None;
+                for &var in rest {
+                    let next = self.new_var();
+                    self.add_clause(vec![-var, next]);
+                    if let Some(seen) = seen {
+                        self.add_clause(vec![-seen, next]);
+                        self.add_clause(vec![-var, -seen]);
+                    }
+                    seen = Some(next);
+                }
+                if let Some(seen) = seen {
+                    self.add_clause(vec![-last, -seen]);
+                }
+            }
+        }
+    }
+
+    /// Writes the formula in DIMACS format.
+    #[cfg(feature = "std")]
+    pub fn write_dimacs(&self, mut out: impl io::Write) -> io::Result<()> {
+        out.write_all(self.to_dimacs().as_bytes())
+    }
+
+    pub fn to_dimacs(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "p cnf {} {}", self.num_vars, self.clauses.len()).expect("writing to a String cannot fail");
+        for clause in &self.clauses {
+            for literal in clause {
+                write!(out, "{} ", literal).expect("writing to a String cannot fail");
+            }
+            out.push_str("0\n");
+        }
+        out
+    }
+}
+
+/// Turns puzzles into CNF under a region layout and variant constraints.
+///
+/// Rows, columns and the regions of the layout are encoded exactly.
+/// Constraints are opaque to the encoder, so it asks each one which digits
+/// it rules out next to every single placed digit and writes those as binary
+/// clauses. That covers constraints between pairs of cells such as
+/// anti-knight or Kropki dots, but only part of those over longer lines like
+/// arrows; `SolverSat` checks the models it gets back for the rest.
+#[derive(Clone)]
+pub struct Encoder {
+    encoding: Encoding,
+    at_most_one: AtMostOne,
+    layout: Arc<Layout>,
+    constraints: Vec<Arc<dyn Constraint>>,
+}
+
+impl Encoder {
+    pub fn new(encoding: Encoding) -> Self {
+        Self {
+            encoding,
+            at_most_one: AtMostOne::Pairwise,
+            layout: Arc::new(Layout::standard()),
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn at_most_one(mut self, at_most_one: AtMostOne) -> Self {
+        self.at_most_one = at_most_one;
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = Arc::new(layout);
+        self
+    }
+
+    pub fn constraints(mut self, constraints: impl IntoIterator<Item = Arc<dyn Constraint>>) -> Self {
+        self.constraints.extend(constraints);
+        self
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn constraints_ref(&self) -> &[Arc<dyn Constraint>] {
+        &self.constraints
+    }
+
+    fn units(&self) -> Vec<[usize; 9]> {
+        let mut units = Vec::new();
+        for i in 0..9 {
+            units.push(core::array::from_fn(|j| row_cell(i, j)));
+            units.push(core::array::from_fn(|j| col_cell(i, j)));
+        }
+        let mut boxes = [[0; 9]; 9];
+        let mut filled = [0; 9];
+        for cell in 0..N_CELLS {
+            let index = self.layout.box_of(cell);
+            boxes[index][filled[index]] = cell;
+            filled[index] += 1;
+        }
+        units.extend(boxes);
+        units.extend(self.layout.regions());
+        units
+    }
+
+    /// Clauses for the digits the constraints rule out, on their own and
+    /// next to each single placed digit.
+    fn constraint_clauses(&self, cnf: &mut Cnf) {
+        let mut values = [0; N_CELLS];
+        let mut seen = BTreeSet::new();
+        for constraint in &self.constraints {
+            let base = core::array::from_fn::<_, N_CELLS, _>(|cell| constraint.allowed(&values, cell));
+            for (cell, &allowed) in base.iter().enumerate() {
+                for value in (1..=9).filter(|&value| allowed & bit(value) == 0) {
+                    cnf.add_clause(vec![-var(cell, value)]);
+                }
+            }
+            for other in 0..N_CELLS {
+                for other_value in (1..=9).filter(|&value| base[other] & bit(value) != 0) {
+                    values[other] = other_value;
+                    for cell in (0..N_CELLS).filter(|&cell| cell != other) {
+                        let ruled_out = base[cell] & !constraint.allowed(&values, cell);
+                        for value in (1..=9).filter(|&value| ruled_out & bit(value) != 0) {
+                            let mut clause = vec![-var(cell, value), -var(other, other_value)];
+                            clause.sort_unstable();
+                            if seen.insert(clause.clone()) {
+                                cnf.add_clause(clause);
+                            }
+                        }
+                    }
+                }
+                values[other] = 0;
+            }
+        }
+    }
+
+    /// The clauses for the rules alone, shared by every puzzle.
+    pub fn rules(&self) -> Cnf {
+        let mut cnf = Cnf {
+            num_vars: CELL_VARS,
+            clauses: Vec::new(),
+        };
+        for cell in 0..N_CELLS {
+            cnf.add_clause((1..=9).map(|value| var(cell, value)).collect());
+        }
+        let units = self.units();
+        for unit in &units {
+            for value in 1..=9 {
+                let vars = unit.map(|cell| var(cell, value));
+                cnf.at_most_one(&vars, self.at_most_one);
+            }
+        }
+        if self.encoding == Encoding::Extended {
+            for cell in 0..N_CELLS {
+                let vars = core::array::from_fn::<_, 9, _>(|value| var(cell, value as u8 + 1));
+                cnf.at_most_one(&vars, self.at_most_one);
+            }
+            for unit in &units {
+                for value in 1..=9 {
+                    cnf.add_clause(unit.iter().map(|&cell| var(cell, value)).collect());
+                }
+            }
+        }
+        self.constraint_clauses(&mut cnf);
+        cnf
+    }
+
+    /// The clauses for the rules and the givens of `puzzle`.
+    pub fn encode(&self, puzzle: &Puzzle) -> Cnf {
+        let mut cnf = self.rules();
+        cnf.add_givens(puzzle);
+        cnf
+    }
+}
+
+/// The grid a model assigns, `.` for a cell with no true variable. The
+/// model lists literals as a SAT solver prints them; auxiliary variables are
+/// ignored.
+pub fn decode(model: &[i32]) -> Sudoku {
+    let mut grid = [b'.'; N_CELLS];
+    for &literal in model {
+        if literal > 0 && literal as usize <= CELL_VARS {
+            let index = literal as usize - 1;
+            grid[index / 9] = b'1' + (index % 9) as u8;
+        }
+    }
+    Sudoku::new(grid)
+}
+
+/// Reads a SAT solver's answer, either the competition format on standard
+/// output (`s SATISFIABLE` then `v` lines) or MiniSat's result file (`SAT`
+/// then the literals). Returns `None` for an unsatisfiable formula.
+pub fn parse_model(text: &str) -> Result<Option<Vec<i32>>> {
+    let mut satisfiable = None;
+    let mut model = Vec::new();
+    for line in text.lines().map(str::trim) {
+        let literals = match line.split_once(char::is_whitespace).unwrap_or((line, "")) {
+            ("s", "SATISFIABLE") | ("SAT", _) => {
+                satisfiable = Some(true);
+                continue;
+            }
+            ("s", "UNSATISFIABLE") | ("UNSAT", _) => {
+                satisfiable = Some(false);
+                continue;
+            }
+            ("v", literals) => literals,
+            _ if satisfiable == Some(true) && !line.starts_with('c') => line,
+            _ => continue,
+        };
+        for literal in literals.split_whitespace() {
+            let literal = literal
+                .parse::<i32>()
+                .map_err(|_| Error::Format(format!("invalid literal '{}' in SAT model", literal)))?;
+            if literal != 0 {
+                model.push(literal);
+            }
+        }
+    }
+    match satisfiable {
+        Some(true) => Ok(Some(model)),
+        Some(false) => Ok(None),
+        None => Err(Error::Format("SAT solver output has no result line".into())),
+    }
+}
+
+#[cfg(feature = "std")]
+/// A clause ruling out the digits `sudoku` places in the cells that are not
+/// given in `puzzle`.
+pub(crate) fn blocking_clause(puzzle: &Puzzle, sudoku: &Sudoku) -> Vec<i32> {
+    (0..N_CELLS)
+        .filter(|&cell| value_of(puzzle.grid[cell]).is_none())
+        .filter_map(|cell| value_of(sudoku.grid[cell]).map(|value| -var(cell, value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{Solver, SolverBasic};
+
+    const EASY: &[u8] = include_bytes!("../corpora/easy.txt");
+
+    fn puzzle() -> Puzzle<'static> {
+        Puzzle::new(&EASY[..N_CELLS])
+    }
+
+    fn solution() -> Sudoku {
+        let solver = SolverBasic::new(1, true);
+        solver.solve(&puzzle(), &mut solver.make_state()).unwrap().sudoku
+    }
+
+    /// A model of `cnf` that puts `grid` in the cells: auxiliary variables
+    /// are set by unit propagation and the rest to false. Panics naming the
+    /// first clause the assignment breaks.
+    fn model_for(cnf: &Cnf, grid: &[u8; N_CELLS]) -> Vec<i32> {
+        let mut values = vec![None; cnf.num_vars + 1];
+        for (cell, &digit) in grid.iter().enumerate() {
+            for value in 1..=9 {
+                values[var(cell, value) as usize] = Some(digit == b'0' + value);
+            }
+        }
+        let truth = |values: &[Option<bool>], literal: i32| {
+            values[literal.unsigned_abs() as usize].map(|value| value == (literal > 0))
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for clause in &cnf.clauses {
+                if clause.iter().any(|&literal| truth(&values, literal) == Some(true)) {
+                    continue;
+                }
+                let mut open = clause.iter().filter(|&&literal| truth(&values, literal).is_none());
+                if let (Some(&literal), None) = (open.next(), open.next()) {
+                    values[literal.unsigned_abs() as usize] = Some(literal > 0);
+                    changed = true;
+                }
+            }
+        }
+        for clause in &cnf.clauses {
+            assert!(
+                clause.iter().any(|&literal| truth(&values, literal).unwrap_or(literal < 0)),
+                "clause {:?} is broken",
+                clause
+            );
+        }
+        (1..=cnf.num_vars as i32)
+            .map(|variable| match values[variable as usize] {
+                Some(true) => variable,
+                _ => -variable,
+            })
+            .collect()
+    }
+
+    fn competition_output(model: &[i32]) -> String {
+        let mut out = String::from("c a comment\ns SATISFIABLE\n");
+        for line in model.chunks(10) {
+            out.push('v');
+            for literal in line {
+                write!(out, " {}", literal).unwrap();
+            }
+            out.push('\n');
+        }
+        out.push_str("v 0\n");
+        out
+    }
+
+    fn minisat_output(model: &[i32]) -> String {
+        let mut out = String::from("SAT\n");
+        for literal in model {
+            write!(out, "{} ", literal).unwrap();
+        }
+        out.push_str("0\n");
+        out
+    }
+
+    #[test]
+    fn models_round_trip_through_parse_model_and_decode() {
+        let solution = solution();
+        for at_most_one in [AtMostOne::Pairwise, AtMostOne::Sequential] {
+            for encoding in [Encoding::Minimal, Encoding::Extended] {
+                let cnf = Encoder::new(encoding).at_most_one(at_most_one).encode(&puzzle());
+                let model = model_for(&cnf, &solution.grid);
+                for text in [competition_output(&model), minisat_output(&model)] {
+                    let parsed = parse_model(&text).unwrap().unwrap();
+                    assert_eq!(parsed, model, "{:?} {:?}", at_most_one, encoding);
+                    assert_eq!(decode(&parsed), solution, "{:?} {:?}", at_most_one, encoding);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "is broken")]
+    fn sequential_encoding_rejects_a_digit_twice_in_a_row() {
+        let mut grid = solution().grid;
+        grid.swap(0, 9);
+        let cnf = Encoder::new(Encoding::Minimal).at_most_one(AtMostOne::Sequential).rules();
+        model_for(&cnf, &grid);
+    }
+
+    #[test]
+    #[should_panic(expected = "is broken")]
+    fn pairwise_encoding_rejects_a_digit_twice_in_a_row() {
+        let mut grid = solution().grid;
+        grid.swap(0, 9);
+        let cnf = Encoder::new(Encoding::Minimal).at_most_one(AtMostOne::Pairwise).rules();
+        model_for(&cnf, &grid);
+    }
+
+    #[test]
+    fn decode_ignores_auxiliary_variables_and_leaves_unset_cells_empty() {
+        let model = [var(0, 5), -var(1, 3), CELL_VARS as i32 + 1, var(80, 9)];
+        let grid = decode(&model).grid;
+        assert_eq!((grid[0], grid[1], grid[80]), (b'5', b'.', b'9'));
+        assert_eq!(grid.iter().filter(|&&digit| digit == b'.').count(), N_CELLS - 2);
+    }
+
+    #[test]
+    fn parse_model_reads_unsatisfiable_and_rejects_garbage() {
+        assert_eq!(parse_model("s UNSATISFIABLE\n").unwrap(), None);
+        assert_eq!(parse_model("UNSAT\n").unwrap(), None);
+        assert!(parse_model("c nothing to see\n").is_err());
+        assert!(parse_model("s SATISFIABLE\nv 1 x 0\n").is_err());
+    }
+}
@@ -0,0 +1,458 @@
+use std::{
+    io::{self, Read},
+    net::{SocketAddr, ToSocketAddrs},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    error::{Error, Result},
+    format::Format,
+    hint::rate_with_solution,
+    solver::{Budget, Outcome, Solver},
+    sudoku::{Puzzle, Sudoku},
+    types::ChunkStats,
+};
+
+/// Largest request body read, in bytes.
+const MAX_BODY: u64 = 16 * 1024 * 1024;
+
+/// Totals reported on `/stats`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ServiceStats {
+    pub requests: usize,
+    /// Requests answered with an error status.
+    pub errors: usize,
+    /// Puzzles solved for `/solve`, `/batch` and `/unique`, one chunk per request.
+    pub puzzles: ChunkStats,
+}
+
+/// Builder for an HTTP service answering solve requests.
+///
+/// | Endpoint       | Body                                          | Answer                                  |
+/// |----------------|-----------------------------------------------|-----------------------------------------|
+/// | `POST /solve`  | one puzzle, or `{"puzzle": "..."}`            | the solution and guesses                |
+/// | `POST /batch`  | puzzles in any `Format`, or `{"puzzles": []}` | `{"results": [...]}`, one per puzzle    |
+/// | `POST /unique` | one puzzle, or `{"puzzle": "..."}`            | whether it has exactly one solution     |
+/// | `POST /rate`   | one puzzle, or `{"puzzle": "..."}`            | the hardest technique needed and steps  |
+/// | `GET /stats`   |                                               | `ServiceStats`                          |
+///
+/// Answers are JSON. A fixed pool of threads takes requests off one
+/// listener, each keeping its own `Solver::State` for every request it
+/// handles. `/unique` and `/rate` need a solver that looks for at least two
+/// solutions. Rating solves the puzzle once within the budget, answering 503
+/// if it runs out, then follows the hints against that solution.
+pub struct Service<S: Solver> {
+    solver: S,
+    num_threads: usize,
+    budget: Budget,
+}
+
+impl<S: Solver> Service<S> {
+    pub fn new(solver: S) -> Self {
+        Self {
+            solver,
+            num_threads: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            budget: Budget::unlimited(),
+        }
+    }
+
+    pub fn threads(mut self, num_threads: usize) -> Self {
+        self.num_threads = num_threads.max(1);
+        self
+    }
+
+    /// Per-puzzle limits; puzzles that exceed them are answered with `"status": "gave_up"`.
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Listens on `addr` and starts the worker threads. Port 0 picks a free
+    /// port, see `ServiceHandle::addr`.
+    pub fn bind(self, addr: impl ToSocketAddrs) -> Result<ServiceHandle> {
+        let server = Server::http(addr).map_err(|err| Error::Pipeline(format!("cannot listen: {}", err)))?;
+        let addr = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| Error::Pipeline("service is not listening on an IP address".into()))?;
+        let shared = Arc::new(Shared {
+            server,
+            stopping: AtomicBool::new(false),
+            failure: Mutex::new(None),
+            num_threads: self.num_threads,
+        });
+        let stats = Arc::new(Mutex::new(ServiceStats::default()));
+
+        let threads = (0..self.num_threads)
+            .map(|_| {
+                let (solver, budget, stats) = (self.solver.clone(), self.budget, stats.clone());
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    let handler = Handler {
+                        state: solver.make_state(),
+                        solver,
+                        budget,
+                        stats,
+                    };
+                    handler.run(&shared)
+                })
+            })
+            .collect();
+
+        Ok(ServiceHandle {
+            shared,
+            addr,
+            stats,
+            threads,
+        })
+    }
+}
+
+/// What the worker threads share besides the stats.
+struct Shared {
+    server: Server,
+    stopping: AtomicBool,
+    /// Why the listener stopped taking connections, if it did.
+    failure: Mutex<Option<io::Error>>,
+    num_threads: usize,
+}
+
+impl Shared {
+    /// Wakes every worker thread so it sees `stopping` and returns.
+    fn stop(&self) {
+        self.stopping.store(true, Ordering::Relaxed);
+        for _ in 0..self.num_threads {
+            self.server.unblock();
+        }
+    }
+}
+
+/// A running service, stopped by `shutdown`.
+pub struct ServiceHandle {
+    shared: Arc<Shared>,
+    addr: SocketAddr,
+    stats: Arc<Mutex<ServiceStats>>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl ServiceHandle {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn stats(&self) -> ServiceStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Blocks for as long as the service runs. Fails with the listener's
+    /// error if it stopped taking connections.
+    pub fn join(self) -> Result<()> {
+        for handle in self.threads {
+            handle
+                .join()
+                .map_err(|_| Error::Pipeline("service thread panicked".into()))?;
+        }
+        match self.shared.failure.lock().unwrap().take() {
+            Some(err) => Err(Error::Io(err)),
+            None => Ok(()),
+        }
+    }
+
+    /// Lets requests in progress finish, then stops the worker threads.
+    pub fn shutdown(self) -> Result<()> {
+        self.shared.stop();
+        self.join()
+    }
+}
+
+/// A failed request: the status code and the message for `{"error": ...}`.
+struct Failure(u16, String);
+
+impl From<Error> for Failure {
+    fn from(err: Error) -> Self {
+        Failure(400, err.to_string())
+    }
+}
+
+struct Handler<S: Solver> {
+    solver: S,
+    state: S::State,
+    budget: Budget,
+    stats: Arc<Mutex<ServiceStats>>,
+}
+
+impl<S: Solver> Handler<S> {
+    fn run(mut self, shared: &Shared) {
+        loop {
+            match shared.server.recv() {
+                Ok(request) => self.respond(request),
+                Err(_) if shared.stopping.load(Ordering::Relaxed) => break,
+                // The listener gave up accepting, so no more requests will come
+                Err(err) => {
+                    shared.failure.lock().unwrap().get_or_insert(err);
+                    shared.stop();
+                    break;
+                }
+            }
+        }
+    }
+
+    fn respond(&mut self, mut request: Request) {
+        let mut body = String::new();
+        let answer = match request.as_reader().take(MAX_BODY + 1).read_to_string(&mut body) {
+            Ok(_) if body.len() as u64 > MAX_BODY => Err(Failure(413, "request body is too large".into())),
+            Ok(_) => self.route(request.method(), request.url(), &body),
+            Err(err) => Err(Failure(400, format!("cannot read request body: {}", err))),
+        };
+
+        let (status, value) = match answer {
+            Ok(value) => (200, value),
+            Err(Failure(status, message)) => (status, json!({ "error": message })),
+        };
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.requests += 1;
+            if status >= 400 {
+                stats.errors += 1;
+            }
+        }
+        let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).expect("header is valid");
+        let response = Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(header);
+        // The client hanging up is not our problem
+        let _ = request.respond(response);
+    }
+
+    fn route(&mut self, method: &Method, url: &str, body: &str) -> std::result::Result<Value, Failure> {
+        let path = url.split('?').next().unwrap_or(url);
+        match (method, path) {
+            (Method::Post, "/solve") => {
+                let sudoku = parse_one(body)?;
+                Ok(self.solve(&[sudoku]).remove(0))
+            }
+            (Method::Post, "/batch") => Ok(json!({ "results": self.solve(&parse_many(body)?) })),
+            (Method::Post, "/unique") => {
+                let sudoku = parse_one(body)?;
+                let mut answer = self.solve(&[sudoku]).remove(0);
+                let unique = answer["status"] == "solved" && answer["solutions"] == 1;
+                answer["unique"] = unique.into();
+                Ok(answer)
+            }
+            (Method::Post, "/rate") => {
+                let sudoku = parse_one(body)?;
+                let outcome = self.solver.solve_within(&Puzzle::new(&sudoku.grid), &mut self.state, &self.budget);
+                let solution = match outcome {
+                    Outcome::Solved(solution) if solution.solutions == 1 => solution.sudoku,
+                    Outcome::Solved(_) | Outcome::NoSolution => {
+                        return Err(Failure(422, "puzzle does not have a unique solution".into()))
+                    }
+                    Outcome::GaveUp { .. } => return Err(Failure(503, "puzzle ran out of budget".into())),
+                };
+                let rating = rate_with_solution(&sudoku, &solution);
+                Ok(json!({
+                    "puzzle": sudoku,
+                    "hardest": rating.hardest.name(),
+                    "steps": rating.steps,
+                }))
+            }
+            (Method::Get, "/stats") => {
+                serde_json::to_value(&*self.stats.lock().unwrap()).map_err(|err| Failure(500, err.to_string()))
+            }
+            (_, "/solve" | "/batch" | "/unique" | "/rate" | "/stats") => {
+                Err(Failure(405, format!("{} is not allowed on {}", method, path)))
+            }
+            _ => Err(Failure(404, format!("no endpoint {}", path))),
+        }
+    }
+
+    /// Solves each puzzle within the budget and adds them to the stats.
+    fn solve(&mut self, sudokus: &[Sudoku]) -> Vec<Value> {
+        let start = Instant::now();
+        let mut stats = ChunkStats {
+            chunks: 1,
+            ..ChunkStats::default()
+        };
+        let answers = sudokus
+            .iter()
+            .map(|sudoku| {
+                stats.puzzles += 1;
+                match self.solver.solve_within(&Puzzle::new(&sudoku.grid), &mut self.state, &self.budget) {
+                    Outcome::Solved(solution) => {
+                        stats.solutions += 1;
+                        stats.guesses += solution.guesses;
+                        if solution.guesses == 0 {
+                            stats.no_guesses += 1;
+                        }
+                        json!({
+                            "puzzle": sudoku,
+                            "status": "solved",
+                            "solution": solution.sudoku,
+                            "guesses": solution.guesses,
+                            "solutions": solution.solutions,
+                        })
+                    }
+                    Outcome::NoSolution => json!({ "puzzle": sudoku, "status": "no_solution" }),
+                    Outcome::GaveUp { nodes, guesses } => {
+                        stats.gave_up += 1;
+                        stats.guesses += guesses;
+                        json!({ "puzzle": sudoku, "status": "gave_up", "nodes": nodes })
+                    }
+                }
+            })
+            .collect();
+        stats.elapsed = start.elapsed();
+        self.stats.lock().unwrap().puzzles.add(&stats);
+        answers
+    }
+}
+
+/// A JSON body, if the body looks like one.
+fn parse_json(body: &str) -> std::result::Result<Option<Value>, Failure> {
+    if !body.trim_start().starts_with('{') {
+        return Ok(None);
+    }
+    serde_json::from_str(body)
+        .map(Some)
+        .map_err(|err| Failure(400, format!("invalid JSON: {}", err)))
+}
+
+fn parse_one(body: &str) -> std::result::Result<Sudoku, Failure> {
+    match parse_json(body)? {
+        Some(value) => match value["puzzle"].as_str() {
+            Some(puzzle) => Ok(Sudoku::from_str(puzzle)?),
+            None => Err(Failure(400, "expected {\"puzzle\": \"...\"}".into())),
+        },
+        None => Ok(Sudoku::from_str(body)?),
+    }
+}
+
+fn parse_many(body: &str) -> std::result::Result<Vec<Sudoku>, Failure> {
+    match parse_json(body)? {
+        Some(value) => match value["puzzles"].as_array() {
+            Some(puzzles) => puzzles
+                .iter()
+                .map(|puzzle| match puzzle.as_str() {
+                    Some(puzzle) => Ok(Sudoku::from_str(puzzle)?),
+                    None => Err(Failure(400, "puzzles must be strings".into())),
+                })
+                .collect(),
+            None => Err(Failure(400, "expected {\"puzzles\": [...]}".into())),
+        },
+        None => Ok(Format::detect(body)
+            .parse(body)?
+            .into_iter()
+            .map(|record| record.sudoku)
+            .collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, net::TcpStream};
+
+    use super::*;
+    use crate::{solver::SolverBasic, sudoku::N_CELLS};
+
+    const EASY: &[u8] = include_bytes!("../corpora/easy.txt");
+    const HARD: &[u8] = include_bytes!("../corpora/hard.txt");
+
+    fn puzzle(corpus: &[u8], index: usize) -> &str {
+        std::str::from_utf8(&corpus[index * (N_CELLS + 1)..][..N_CELLS]).unwrap()
+    }
+
+    fn start(budget: Budget) -> ServiceHandle {
+        Service::new(SolverBasic::new(2, true))
+            .threads(2)
+            .budget(budget)
+            .bind("127.0.0.1:0")
+            .unwrap()
+    }
+
+    /// Sends one request and returns the status code and the JSON answer.
+    fn call(handle: &ServiceHandle, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(handle.addr()).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn answers_every_endpoint_on_localhost() {
+        let handle = start(Budget::unlimited());
+        let (easy, empty) = (puzzle(EASY, 0), ".".repeat(N_CELLS));
+
+        let (status, answer) = call(&handle, "POST", "/solve", easy);
+        assert_eq!((status, answer["status"].as_str()), (200, Some("solved")));
+        let solution = Sudoku::from_str(answer["solution"].as_str().unwrap()).unwrap();
+        assert!(solution.solves(&Sudoku::from_str(easy).unwrap()));
+
+        let body = json!({ "puzzles": [easy, puzzle(EASY, 1), "1".repeat(N_CELLS)] }).to_string();
+        let (status, answer) = call(&handle, "POST", "/batch", &body);
+        let statuses = answer["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| result["status"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!((status, statuses), (200, vec!["solved", "solved", "no_solution"]));
+
+        let (status, answer) = call(&handle, "POST", "/unique", &json!({ "puzzle": easy }).to_string());
+        assert_eq!((status, answer["unique"].as_bool()), (200, Some(true)));
+        let (status, answer) = call(&handle, "POST", "/unique", &empty);
+        assert_eq!((status, answer["unique"].as_bool()), (200, Some(false)));
+
+        let (status, answer) = call(&handle, "POST", "/rate", easy);
+        assert_eq!(status, 200);
+        assert!(answer["hardest"].is_string() && answer["steps"].as_u64() > Some(0));
+        assert_eq!(call(&handle, "POST", "/rate", &empty).0, 422);
+
+        assert_eq!(call(&handle, "POST", "/solve", &"x".repeat(N_CELLS)).0, 400);
+        assert_eq!(call(&handle, "GET", "/solve", "").0, 405);
+        assert_eq!(call(&handle, "GET", "/nowhere", "").0, 404);
+
+        let (status, answer) = call(&handle, "GET", "/stats", "");
+        assert_eq!(status, 200);
+        // The stats request itself is counted once it has been answered
+        assert_eq!(answer["requests"], 9);
+        assert_eq!(answer["errors"], 4);
+        assert_eq!(answer["puzzles"]["puzzles"], 6);
+        handle.shutdown().unwrap();
+    }
+
+    #[test]
+    fn rating_stops_when_the_budget_runs_out() {
+        let handle = start(Budget {
+            max_nodes: Some(1),
+            timeout: None,
+        });
+        let (status, answer) = call(&handle, "POST", "/rate", puzzle(HARD, 0));
+        assert_eq!((status, answer["error"].as_str()), (503, Some("puzzle ran out of budget")));
+        let (status, answer) = call(&handle, "POST", "/solve", puzzle(HARD, 0));
+        assert_eq!((status, answer["status"].as_str()), (200, Some("gave_up")));
+        handle.shutdown().unwrap();
+    }
+}
@@ -1,17 +1,133 @@
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::sudoku::{Puzzle, Sudoku};
 
 mod basic;
+mod gattai;
+mod killer;
+#[cfg(feature = "std")]
+mod sat;
+mod units;
 
-pub use basic::SolverBasic;
+pub use basic::{Solutions, SolverBasic};
+pub use gattai::{GattaiOutcome, GattaiSolution, SolverGattai};
+pub use killer::SolverKiller;
+#[cfg(feature = "std")]
+pub use sat::SolverSat;
+pub(crate) use units::Units;
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SolutionInfo {
     pub sudoku: Sudoku,
     pub guesses: usize,
+    /// Solutions found, up to the solver's limit.
+    pub solutions: usize,
+}
+
+/// Limits on the work a solver may spend on a single puzzle.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Budget {
+    /// Maximum number of search nodes to visit.
+    pub max_nodes: Option<usize>,
+    /// Maximum wall-clock time per puzzle. Ignored without the `std`
+    /// feature, which the clock comes from.
+    pub timeout: Option<Duration>,
+}
+
+impl Budget {
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+}
+
+/// How many nodes to visit between checks of the clock.
+#[cfg(feature = "std")]
+const CLOCK_INTERVAL: usize = 1024;
+
+/// Tracks a search against its budget.
+#[derive(Clone, Debug)]
+pub(crate) struct Meter {
+    pub(crate) nodes: usize,
+    max_nodes: usize,
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
+    pub(crate) gave_up: bool,
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Self {
+            nodes: 0,
+            max_nodes: usize::MAX,
+            #[cfg(feature = "std")]
+            deadline: None,
+            gave_up: false,
+        }
+    }
+}
+
+impl Meter {
+    #[inline]
+    pub(crate) fn start(&mut self, budget: &Budget) {
+        self.nodes = 0;
+        self.max_nodes = budget.max_nodes.unwrap_or(usize::MAX);
+        #[cfg(feature = "std")]
+        {
+            self.deadline = budget.timeout.map(|timeout| Instant::now() + timeout);
+        }
+        self.gave_up = false;
+    }
+
+    /// Whether the budget sets any limit; searches may skip `tick` if not.
+    #[inline]
+    pub(crate) fn is_limited(&self) -> bool {
+        #[cfg(feature = "std")]
+        if self.deadline.is_some() {
+            return true;
+        }
+        self.max_nodes != usize::MAX
+    }
+
+    /// Counts a search node and reports whether the budget has run out.
+    #[inline]
+    pub(crate) fn tick(&mut self) -> bool {
+        self.nodes += 1;
+        if self.nodes > self.max_nodes {
+            self.gave_up = true;
+        }
+        #[cfg(feature = "std")]
+        if !self.gave_up && self.nodes.is_multiple_of(CLOCK_INTERVAL) {
+            if let Some(deadline) = self.deadline {
+                self.gave_up = Instant::now() >= deadline;
+            }
+        }
+        self.gave_up
+    }
+}
+
+pub enum Outcome {
+    Solved(SolutionInfo),
+    NoSolution,
+    /// The budget ran out before the search finished.
+    GaveUp { nodes: usize, guesses: usize },
 }
 
 pub trait Solver: Clone + Send + Sync + 'static {
     type State;
 
     fn make_state(&self) -> Self::State;
-    fn solve(&self, puzzle: &Puzzle, state: &mut Self::State) -> Option<SolutionInfo>;
+    fn solve_within(&self, puzzle: &Puzzle, state: &mut Self::State, budget: &Budget) -> Outcome;
+
+    fn solve(&self, puzzle: &Puzzle, state: &mut Self::State) -> Option<SolutionInfo> {
+        match self.solve_within(puzzle, state, &Budget::unlimited()) {
+            Outcome::Solved(solution) => Some(solution),
+            _ => None,
+        }
+    }
 }
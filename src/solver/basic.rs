@@ -1,34 +1,110 @@
+use alloc::{sync::Arc, vec, vec::Vec};
+
+use crate::constraint::Constraint;
+use crate::layout::Layout;
 use crate::solver::Solver;
-use crate::sudoku::{Puzzle, Sudoku};
+use crate::sudoku::{Puzzle, Sudoku, N_CELLS};
 
-use super::SolutionInfo;
+use super::{Budget, Meter, Outcome, SolutionInfo};
 
 type Bits = u32;
 const ALL: Bits = 0x1ff;
 
 type RowColSub = (usize, usize, usize);
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct BasicState {
     rows: [Bits; 9],
     cols: [Bits; 9],
     subs: [Bits; 9],
+    /// Digits still free in each extra region of the layout.
+    regions: Vec<Bits>,
+    layout: Arc<Layout>,
+    constraints: Vec<Arc<dyn Constraint>>,
+    /// Digits placed so far, `0` for empty, kept only for the constraints.
+    values: [u8; N_CELLS],
     todo: Vec<RowColSub>,
     num_todo: usize,
     guesses: usize,
     num_solutions: usize,
+    first_solution: Option<Sudoku>,
+    meter: Meter,
+}
+
+impl Default for BasicState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BasicState {
     pub fn new() -> Self {
+        Self::with_rules(Arc::new(Layout::standard()), Vec::new())
+    }
+
+    fn with_rules(layout: Arc<Layout>, constraints: Vec<Arc<dyn Constraint>>) -> Self {
         Self {
             rows: [ALL; 9],
             cols: [ALL; 9],
             subs: [ALL; 9],
+            regions: vec![ALL; layout.num_regions()],
+            layout,
+            constraints,
+            values: [0; N_CELLS],
             todo: vec![],
             num_todo: 0,
             guesses: 0,
             num_solutions: 0,
+            first_solution: None,
+            meter: Meter::default(),
+        }
+    }
+
+    /// Whether the rules are the standard rows, columns and boxes alone.
+    fn is_plain(&self) -> bool {
+        self.regions.is_empty() && self.constraints.is_empty()
+    }
+
+    /// Digits `(row, col, sub)` may still take. `PLAIN` skips the extra
+    /// regions and constraints, for callers that know there are none.
+    #[inline(always)]
+    fn candidates<const PLAIN: bool>(&self, (row, col, sub): RowColSub) -> Bits {
+        let mut candidates = self.rows[row] & self.cols[col] & self.subs[sub];
+        if PLAIN {
+            return candidates;
+        }
+        if !self.regions.is_empty() {
+            for &region in self.layout.regions_of(row * 9 + col) {
+                candidates &= self.regions[region as usize];
+            }
+        }
+        for constraint in &self.constraints {
+            candidates &= constraint.allowed(&self.values, row * 9 + col) as Bits;
+        }
+        candidates
+    }
+
+    /// Takes `value` out of, or puts it back into, the units of a cell.
+    /// `PLAIN` as for `candidates`.
+    #[inline(always)]
+    fn toggle<const PLAIN: bool>(&mut self, (row, col, sub): RowColSub, value: Bits) {
+        self.rows[row] ^= value;
+        self.cols[col] ^= value;
+        self.subs[sub] ^= value;
+        if PLAIN {
+            return;
+        }
+        if !self.regions.is_empty() {
+            for &region in self.layout.regions_of(row * 9 + col) {
+                self.regions[region as usize] ^= value;
+            }
+        }
+        if !self.constraints.is_empty() {
+            let cell = &mut self.values[row * 9 + col];
+            *cell = match *cell {
+                0 => value.trailing_zeros() as u8 + 1,
+                _ => 0,
+            };
         }
     }
 
@@ -36,8 +112,11 @@ impl BasicState {
         self.rows.fill(ALL);
         self.cols.fill(ALL);
         self.subs.fill(ALL);
+        self.regions.fill(ALL);
+        self.values.fill(0);
         self.guesses = 0;
         self.num_solutions = 0;
+        self.first_solution = None;
 
         // Copy initial clues to the solution since our todo list won't include these cells.
         self.todo.clear();
@@ -45,47 +124,39 @@ impl BasicState {
         for row in 0..9 {
             for col in 0..9 {
                 let cell = row * 9 + col;
-                let sub = (row / 3) * 3 + col / 3;
+                let sub = self.layout.box_of(cell);
                 solution.grid[cell] = puzzle.grid[cell];
                 if b'1' <= puzzle.grid[cell] && puzzle.grid[cell] <= b'9' {
-                    // A given clue: clear availability bits for row, col, and box.
+                    // A given clue: clear availability bits for row, col, box and regions.
                     let value = 1u32 << (puzzle.grid[cell] as u32 - b'1' as u32);
-                    if self.rows[row] & value != 0
-                        && self.cols[col] & value != 0
-                        && self.subs[sub] & value != 0
-                    {
-                        self.rows[row] ^= value;
-                        self.cols[col] ^= value;
-                        self.subs[sub] ^= value;
-                    } else {
-                        println!(
-                            "Invalid puzzle: row: {}, col: {}, sub: {}, value: {}",
-                            row, col, sub, value
-                        );
+                    if self.candidates::<false>((row, col, sub)) & value == 0 {
+                        // The clue repeats a digit, so there is no solution
                         return false;
                     }
+                    self.toggle::<false>((row, col, sub), value);
                 } else {
                     self.todo.push((row, col, sub));
                 }
             }
         }
-        self.num_todo = self.todo.len() - 1;
+        self.num_todo = self.todo.len().saturating_sub(1);
         true
     }
 
-    fn mcv(&mut self, todo_index: usize) {
-        let sublist = &mut self.todo[todo_index..];
-        if let Some(min_element_index) = sublist
-            .iter()
-            .enumerate()
-            .min_by_key(|&(_, &(row, col, sub))| {
-                let candidates = self.rows[row] & self.cols[col] & self.subs[sub];
-                candidates.count_ones()
-            })
-            .map(|(index, _)| index)
-        {
-            sublist.swap(0, min_element_index);
+    /// Moves the first cell with the fewest candidates to `todo_index`.
+    ///
+    /// A plain loop rather than `min_by_key`: this runs on every search node,
+    /// and the iterator version is not reliably inlined across codegen units.
+    #[inline(always)]
+    fn mcv<const PLAIN: bool>(&mut self, todo_index: usize) {
+        let (mut min_index, mut min_count) = (todo_index, u32::MAX);
+        for index in todo_index..self.todo.len() {
+            let count = self.candidates::<PLAIN>(self.todo[index]).count_ones();
+            if count < min_count {
+                (min_index, min_count) = (index, count);
+            }
         }
+        self.todo.swap(todo_index, min_index);
     }
 }
 
@@ -93,6 +164,8 @@ impl BasicState {
 pub struct SolverBasic {
     limit: usize,
     min_heuristic: bool,
+    layout: Arc<Layout>,
+    constraints: Vec<Arc<dyn Constraint>>,
 }
 
 impl SolverBasic {
@@ -100,17 +173,48 @@ impl SolverBasic {
         Self {
             limit,
             min_heuristic,
+            layout: Arc::new(Layout::standard()),
+            constraints: Vec::new(),
         }
     }
 
-    fn satisfy(&self, todo_index: usize, solution: &mut Sudoku, state: &mut BasicState) -> bool {
+    /// Solves for `layout` instead of the standard rows, columns and boxes.
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = Arc::new(layout);
+        self
+    }
+
+    /// Adds a rule for every solution to keep on top of the layout.
+    pub fn constraint(mut self, constraint: impl Constraint + 'static) -> Self {
+        self.constraints.push(Arc::new(constraint));
+        self
+    }
+
+    /// Like `constraint`, for rules that are already shared.
+    pub fn constraints(mut self, constraints: impl IntoIterator<Item = Arc<dyn Constraint>>) -> Self {
+        self.constraints.extend(constraints);
+        self
+    }
+
+    /// Searches from `todo_index` on. `METERED` counts nodes against the
+    /// budget and `PLAIN` leaves out regions and constraints, so a classic
+    /// puzzle with no budget pays for neither.
+    fn satisfy<const METERED: bool, const PLAIN: bool>(
+        &self,
+        todo_index: usize,
+        solution: &mut Sudoku,
+        state: &mut BasicState,
+    ) -> bool {
+        if METERED && state.meter.tick() {
+            return false;
+        }
         if self.min_heuristic {
-            state.mcv(todo_index);
+            state.mcv::<PLAIN>(todo_index);
         }
 
         let (row, col, sub) = state.todo[todo_index];
 
-        let mut candidates = state.rows[row] & state.cols[col] & state.subs[sub];
+        let mut candidates = state.candidates::<PLAIN>((row, col, sub));
         // println!("canditates: {}", candidates.count_ones());
 
         while candidates != 0 {
@@ -123,30 +227,128 @@ impl SolverBasic {
             }
 
             // Clear the candidate from available candidate sets for row, col, box.
-            state.rows[row] ^= candidate;
-            state.cols[col] ^= candidate;
-            state.subs[sub] ^= candidate;
+            state.toggle::<PLAIN>((row, col, sub), candidate);
 
             solution.grid[row * 9 + col] = b'1' + ci;
             // Recursively solve remaining cells and back out with the last solution.
             if todo_index < state.num_todo {
-                self.satisfy(todo_index + 1, solution, state);
+                self.satisfy::<METERED, PLAIN>(todo_index + 1, solution, state);
             } else {
                 state.num_solutions += 1;
+                // Keep the first solution, later ones overwrite the grid while counting
+                if state.num_solutions == 1 && self.limit > 1 {
+                    state.first_solution = Some(*solution);
+                }
             }
 
             if state.num_solutions == self.limit {
                 return true;
             }
+            // Abandon the search; setup resets the bitmasks for the next puzzle
+            if METERED && state.meter.gave_up {
+                return false;
+            }
 
             // Restore the candidate to available candidate sets for row, col, box.
-            state.rows[row] ^= candidate;
-            state.cols[col] ^= candidate;
-            state.subs[sub] ^= candidate;
+            state.toggle::<PLAIN>((row, col, sub), candidate);
 
             candidates ^= candidate;
         }
-        return false;
+        false
+    }
+
+    /// Lazily yields every solution of `puzzle`, ignoring the solution limit.
+    pub fn solutions(&self, puzzle: &Puzzle) -> Solutions<'_> {
+        let mut state = self.make_state();
+        let mut sudoku = puzzle.sudoku();
+        let valid = state.setup(puzzle, &mut sudoku);
+        Solutions {
+            solver: self,
+            state,
+            sudoku,
+            stack: Vec::new(),
+            started: false,
+            done: !valid,
+        }
+    }
+}
+
+/// Candidates still to try at one depth of the search, and the one currently placed.
+struct Frame {
+    candidates: Bits,
+    placed: Bits,
+}
+
+/// Iterator over all solutions of a puzzle, see `SolverBasic::solutions`.
+pub struct Solutions<'a> {
+    solver: &'a SolverBasic,
+    state: BasicState,
+    sudoku: Sudoku,
+    stack: Vec<Frame>,
+    started: bool,
+    done: bool,
+}
+
+impl Solutions<'_> {
+    fn push_frame(&mut self, todo_index: usize) {
+        if self.solver.min_heuristic {
+            self.state.mcv::<false>(todo_index);
+        }
+        let rcs = self.state.todo[todo_index];
+        self.stack.push(Frame {
+            candidates: self.state.candidates::<false>(rcs),
+            placed: 0,
+        });
+    }
+}
+
+impl Iterator for Solutions<'_> {
+    type Item = Sudoku;
+
+    fn next(&mut self) -> Option<Sudoku> {
+        if self.done {
+            return None;
+        }
+        if !self.started {
+            self.started = true;
+            if self.state.todo.is_empty() {
+                self.done = true;
+                return Some(self.sudoku);
+            }
+            self.push_frame(0);
+        }
+
+        // Each pass backs out the value placed at the deepest frame and tries the next one.
+        while !self.stack.is_empty() {
+            let todo_index = self.stack.len() - 1;
+            let frame = &mut self.stack[todo_index];
+            let (row, col, sub) = self.state.todo[todo_index];
+
+            if frame.placed != 0 {
+                let placed = frame.placed;
+                frame.placed = 0;
+                self.state.toggle::<false>((row, col, sub), placed);
+            }
+            if frame.candidates == 0 {
+                self.stack.pop();
+                continue;
+            }
+
+            let ci = frame.candidates.trailing_zeros() as u8;
+            let candidate = 1 << ci;
+            frame.candidates ^= candidate;
+            frame.placed = candidate;
+            self.state.toggle::<false>((row, col, sub), candidate);
+            self.sudoku.grid[row * 9 + col] = b'1' + ci;
+
+            if todo_index == self.state.num_todo {
+                return Some(self.sudoku);
+            }
+            self.push_frame(todo_index + 1);
+        }
+
+        self.done = true;
+        None
     }
 }
 
@@ -154,18 +356,43 @@ impl Solver for SolverBasic {
     type State = BasicState;
 
     fn make_state(&self) -> Self::State {
-        BasicState::default()
+        BasicState::with_rules(Arc::clone(&self.layout), self.constraints.clone())
     }
 
-    fn solve(&self, puzzle: &Puzzle, state: &mut Self::State) -> Option<SolutionInfo> {
+    fn solve_within(&self, puzzle: &Puzzle, state: &mut Self::State, budget: &Budget) -> Outcome {
         let mut solution = puzzle.sudoku();
-        if state.setup(puzzle, &mut solution) && self.satisfy(0, &mut solution, state) {
-            Some(SolutionInfo {
+        if !state.setup(puzzle, &mut solution) {
+            return Outcome::NoSolution;
+        }
+        state.meter.start(budget);
+        // A fully given grid has nothing left to satisfy
+        if state.todo.is_empty() {
+            return Outcome::Solved(SolutionInfo {
                 sudoku: solution,
+                guesses: 0,
+                solutions: 1,
+            });
+        }
+
+        match (state.meter.is_limited(), state.is_plain()) {
+            (false, true) => self.satisfy::<false, true>(0, &mut solution, state),
+            (false, false) => self.satisfy::<false, false>(0, &mut solution, state),
+            (true, true) => self.satisfy::<true, true>(0, &mut solution, state),
+            (true, false) => self.satisfy::<true, false>(0, &mut solution, state),
+        };
+        if state.meter.gave_up {
+            Outcome::GaveUp {
+                nodes: state.meter.nodes,
+                guesses: state.guesses,
+            }
+        } else if state.num_solutions > 0 {
+            Outcome::Solved(SolutionInfo {
+                sudoku: state.first_solution.unwrap_or(solution),
                 guesses: state.guesses,
+                solutions: state.num_solutions,
             })
         } else {
-            None
+            Outcome::NoSolution
         }
     }
 }
@@ -0,0 +1,129 @@
+use alloc::vec::Vec;
+
+use crate::gattai::GattaiPuzzle;
+
+use super::units::{UnitOutcome, UnitSearch};
+use super::Budget;
+
+pub struct GattaiSolution {
+    pub puzzle: GattaiPuzzle,
+    pub guesses: usize,
+    /// Solutions found, up to the solver's limit.
+    pub solutions: usize,
+}
+
+pub enum GattaiOutcome {
+    Solved(GattaiSolution),
+    NoSolution,
+    /// The budget ran out before the search finished.
+    GaveUp { nodes: usize, guesses: usize },
+}
+
+/// Backtracking solver for overlapping grids. Every cell is searched once no
+/// matter how many grids share it, so shared boxes are constrained by the
+/// rows and columns of each grid they belong to.
+///
+/// This does not implement `Solver`: that trait takes a `Puzzle` of one
+/// 81 cell grid and answers with a single `Sudoku`, which is what the batch
+/// and pipeline code built on it read and write, while a Gattai puzzle spans
+/// several grids whose shared cells cannot be split apart. The search state
+/// also borrows the units of the puzzle's own layout, so there is none to
+/// keep between puzzles.
+#[derive(Clone, Copy, Debug)]
+pub struct SolverGattai {
+    limit: usize,
+}
+
+impl SolverGattai {
+    pub fn new(limit: usize) -> Self {
+        Self { limit }
+    }
+
+    pub fn solve_within(&self, puzzle: &GattaiPuzzle, budget: &Budget) -> GattaiOutcome {
+        let givens = puzzle
+            .cells
+            .iter()
+            .map(|&value| match value {
+                b'1'..=b'9' => value - b'0',
+                _ => 0,
+            })
+            .collect::<Vec<_>>();
+        match UnitSearch::new(puzzle.layout.units(), self.limit).run(&givens, budget) {
+            UnitOutcome::Solved {
+                values,
+                guesses,
+                solutions,
+            } => GattaiOutcome::Solved(GattaiSolution {
+                puzzle: GattaiPuzzle {
+                    layout: puzzle.layout.clone(),
+                    cells: values.into_iter().map(|value| value + b'0').collect(),
+                },
+                guesses,
+                solutions,
+            }),
+            UnitOutcome::NoSolution => GattaiOutcome::NoSolution,
+            UnitOutcome::GaveUp { nodes, guesses } => GattaiOutcome::GaveUp { nodes, guesses },
+        }
+    }
+
+    pub fn solve(&self, puzzle: &GattaiPuzzle) -> Option<GattaiSolution> {
+        match self.solve_within(puzzle, &Budget::unlimited()) {
+            GattaiOutcome::Solved(solution) => Some(solution),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gattai::Gattai;
+
+    use super::*;
+
+    const SAMURAI: &str = "
+        .8..52..4.3.7.915..7.1...3........6.8.5....7.79....4.1.53.78..96.749........3....
+        8.7.31..6.13.659.762......8..4..2.6.7...19....6.....9.........3....7...92..1.46..
+        ..9.........579......1682.......5....6..138.....82...1..64.13....16......983.....
+        9.......6.7.6....113.....982.5.3..6.4...28......1.752.6......85...89..43..4....72
+        3....4.5..................95..4..6.3..2.8.9..7...91...4.31..8.2261.43...8597623.1
+    ";
+    const SOLUTION: &str = "
+        186352794234789156579146238341827965865914372792563481453278619617495823928631547
+        897231456413865927625497318984352761752619834361748592578926143146573289239184675
+        619234578823579146547168239182945763964713852375826491256491387431687925798352614
+        948713256572689431136245798215934867467528319389167524623471985751892643894356172
+        387914256925678134614235789598427613142386975736591428473159862261843597859762341
+    ";
+
+    fn samurai() -> GattaiPuzzle {
+        GattaiPuzzle::parse(Gattai::samurai(), SAMURAI).unwrap()
+    }
+
+    #[test]
+    fn solves_a_samurai_to_its_only_solution() {
+        let solution = SolverGattai::new(2).solve(&samurai()).unwrap();
+        assert_eq!(solution.solutions, 1);
+        assert!(solution.puzzle.is_solved());
+        assert_eq!(solution.puzzle, GattaiPuzzle::parse(Gattai::samurai(), SOLUTION).unwrap());
+
+        // The shared corner boxes of the centre grid agree with the outer grids
+        let (centre, corner) = (solution.puzzle.grid(2), solution.puzzle.grid(0));
+        assert_eq!(centre.grid[..3], corner.grid[60..63]);
+    }
+
+    #[test]
+    fn reports_broken_givens_and_spent_budgets() {
+        let mut broken = samurai();
+        let cell = broken.cells.iter().position(|&value| value == b'.').unwrap();
+        broken.cells[cell] = broken.cells[1];
+        assert!(matches!(SolverGattai::new(2).solve_within(&broken, &Budget::unlimited()), GattaiOutcome::NoSolution));
+
+        let empty = GattaiPuzzle::empty(Gattai::samurai());
+        let budget = Budget {
+            max_nodes: Some(10),
+            ..Budget::unlimited()
+        };
+        assert!(matches!(SolverGattai::new(2).solve_within(&empty, &budget), GattaiOutcome::GaveUp { .. }));
+        assert_eq!(SolverGattai::new(2).solve(&empty).unwrap().solutions, 2);
+    }
+}
@@ -0,0 +1,273 @@
+use alloc::{sync::Arc, vec, vec::Vec};
+
+use crate::candidates::{box_of, col_of, row_of};
+use crate::killer::Killer;
+use crate::solver::Solver;
+use crate::sudoku::{Puzzle, Sudoku, N_CELLS};
+
+use super::{Budget, Meter, Outcome, SolutionInfo};
+
+type Bits = u16;
+const ALL: Bits = 0x1ff;
+const MAX_SUM: usize = 45;
+
+/// For each cage size and sum, every set of different digits that adds up to it.
+fn combinations() -> Vec<Vec<Bits>> {
+    let mut combinations = vec![Vec::new(); 10 * (MAX_SUM + 1)];
+    for set in 0..=ALL {
+        let sum = (0..9).filter(|i| set & (1 << i) != 0).map(|i| i + 1).sum::<usize>();
+        combinations[set.count_ones() as usize * (MAX_SUM + 1) + sum].push(set);
+    }
+    combinations
+}
+
+#[derive(Clone, Default)]
+pub struct KillerState {
+    rows: [Bits; 9],
+    cols: [Bits; 9],
+    subs: [Bits; 9],
+    /// Per cage: sum still to be made, cells still empty, digits used so far
+    /// and the digits that can still complete it.
+    sum_left: Vec<usize>,
+    cells_left: Vec<usize>,
+    used: Vec<Bits>,
+    allowed: Vec<Bits>,
+    todo: Vec<usize>,
+    guesses: usize,
+    num_solutions: usize,
+    first_solution: Option<Sudoku>,
+    meter: Meter,
+}
+
+/// Backtracking Killer Sudoku solver. Each cage keeps the union of the digit
+/// combinations that can still complete it, which prunes cells on top of the
+/// row, column and box masks.
+#[derive(Clone)]
+pub struct SolverKiller {
+    killer: Arc<Killer>,
+    combinations: Arc<Vec<Vec<Bits>>>,
+    limit: usize,
+}
+
+impl SolverKiller {
+    pub fn new(killer: Killer, limit: usize) -> Self {
+        Self {
+            killer: Arc::new(killer),
+            combinations: Arc::new(combinations()),
+            limit,
+        }
+    }
+
+    pub fn killer(&self) -> &Killer {
+        &self.killer
+    }
+
+    fn update_allowed(&self, state: &mut KillerState, cage: usize) {
+        let (cells, sum) = (state.cells_left[cage], state.sum_left[cage]);
+        state.allowed[cage] = match sum <= MAX_SUM {
+            true => self.combinations[cells * (MAX_SUM + 1) + sum]
+                .iter()
+                .filter(|&&set| set & state.used[cage] == 0)
+                .fold(0, |allowed, &set| allowed | set),
+            false => 0,
+        };
+    }
+
+    fn candidates(&self, state: &KillerState, cell: usize) -> Bits {
+        let (row, col, sub) = (row_of(cell), col_of(cell), box_of(cell));
+        let candidates = state.rows[row] & state.cols[col] & state.subs[sub];
+        match self.killer.cage_of(cell) {
+            Some(cage) => candidates & state.allowed[cage],
+            None => candidates,
+        }
+    }
+
+    /// Places or removes `value` (1-9) in `cell`, toggling the masks.
+    fn toggle(&self, state: &mut KillerState, cell: usize, value: usize, placing: bool) {
+        let (row, col, sub) = (row_of(cell), col_of(cell), box_of(cell));
+        let bit = 1 << (value - 1);
+        state.rows[row] ^= bit;
+        state.cols[col] ^= bit;
+        state.subs[sub] ^= bit;
+        if let Some(cage) = self.killer.cage_of(cell) {
+            state.used[cage] ^= bit;
+            if placing {
+                state.cells_left[cage] -= 1;
+                state.sum_left[cage] = state.sum_left[cage].wrapping_sub(value);
+            } else {
+                state.cells_left[cage] += 1;
+                state.sum_left[cage] = state.sum_left[cage].wrapping_add(value);
+            }
+            self.update_allowed(state, cage);
+        }
+    }
+
+    /// Resets the state for `puzzle` and returns its grid, or `None` if the
+    /// givens break a rule.
+    fn setup(&self, puzzle: &Puzzle, state: &mut KillerState) -> Option<Sudoku> {
+        let cages = self.killer.cages();
+        state.rows.fill(ALL);
+        state.cols.fill(ALL);
+        state.subs.fill(ALL);
+        state.sum_left = cages.iter().map(|cage| cage.sum as usize).collect();
+        state.cells_left = cages.iter().map(|cage| cage.cells.len()).collect();
+        state.used = vec![0; cages.len()];
+        state.allowed = vec![0; cages.len()];
+        for cage in 0..cages.len() {
+            self.update_allowed(state, cage);
+        }
+        state.todo.clear();
+        state.guesses = 0;
+        state.num_solutions = 0;
+        state.first_solution = None;
+
+        let sudoku = puzzle.sudoku().clean();
+        for cell in 0..N_CELLS {
+            match sudoku.grid[cell] {
+                digit @ b'1'..=b'9' => {
+                    let value = (digit - b'0') as usize;
+                    if self.candidates(state, cell) & (1 << (value - 1)) == 0 {
+                        return None;
+                    }
+                    self.toggle(state, cell, value, true);
+                }
+                _ => state.todo.push(cell),
+            }
+        }
+        // Cages completed by the givens must add up
+        let mut cages_left = state.cells_left.iter().zip(&state.sum_left);
+        cages_left.all(|(&cells, &sum)| cells > 0 || sum == 0).then_some(sudoku)
+    }
+
+    fn satisfy(&self, todo_index: usize, sudoku: &mut Sudoku, state: &mut KillerState) {
+        if state.meter.tick() {
+            return;
+        }
+        let best = (todo_index..state.todo.len())
+            .min_by_key(|&index| self.candidates(state, state.todo[index]).count_ones())
+            .unwrap_or(todo_index);
+        state.todo.swap(todo_index, best);
+
+        let cell = state.todo[todo_index];
+        let mut candidates = self.candidates(state, cell);
+        while candidates != 0 {
+            let value = candidates.trailing_zeros() as usize + 1;
+            candidates &= candidates - 1;
+            if candidates != 0 {
+                state.guesses += 1;
+            }
+
+            self.toggle(state, cell, value, true);
+            sudoku.grid[cell] = b'0' + value as u8;
+            if todo_index + 1 < state.todo.len() {
+                self.satisfy(todo_index + 1, sudoku, state);
+            } else {
+                state.num_solutions += 1;
+                if state.num_solutions == 1 {
+                    state.first_solution = Some(*sudoku);
+                }
+            }
+
+            // Abandon the search; setup resets the masks for the next puzzle
+            if state.num_solutions == self.limit || state.meter.gave_up {
+                return;
+            }
+            self.toggle(state, cell, value, false);
+        }
+        sudoku.grid[cell] = b'.';
+    }
+}
+
+impl Solver for SolverKiller {
+    type State = KillerState;
+
+    fn make_state(&self) -> Self::State {
+        KillerState::default()
+    }
+
+    /// Solves the cage layout with the digits of `puzzle` as extra givens;
+    /// pass an empty grid for a pure Killer.
+    fn solve_within(&self, puzzle: &Puzzle, state: &mut Self::State, budget: &Budget) -> Outcome {
+        let Some(mut sudoku) = self.setup(puzzle, state) else {
+            return Outcome::NoSolution;
+        };
+        state.meter.start(budget);
+        if state.todo.is_empty() {
+            return Outcome::Solved(SolutionInfo {
+                sudoku,
+                guesses: 0,
+                solutions: 1,
+            });
+        }
+
+        self.satisfy(0, &mut sudoku, state);
+        if state.meter.gave_up {
+            Outcome::GaveUp {
+                nodes: state.meter.nodes,
+                guesses: state.guesses,
+            }
+        } else if let Some(sudoku) = state.first_solution {
+            Outcome::Solved(SolutionInfo {
+                sudoku,
+                guesses: state.guesses,
+                solutions: state.num_solutions,
+            })
+        } else {
+            Outcome::NoSolution
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Killer with no givens whose cages only `SOLUTION` satisfies.
+    const LINE: &str = "AABBCCCDDABBEEFFDGHIIEJJKKGHILLMMKNGHHLLMMNNOPPPQQRROOSPTQURVVOSWTUURVVXWWWUYYXXX:\
+                        14,19,24,7,13,8,18,15,22,6,15,30,15,18,16,17,16,28,11,8,10,19,20,22,14";
+    const SOLUTION: &[u8] = b"534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+    const EMPTY: &[u8] = &[b'.'; N_CELLS];
+
+    fn solve(solver: &SolverKiller, grid: &[u8]) -> Outcome {
+        solver.solve_within(&Puzzle::new(grid), &mut solver.make_state(), &Budget::unlimited())
+    }
+
+    #[test]
+    fn combinations_list_every_digit_set_per_sum() {
+        let combinations = combinations();
+        let of = |cells: usize, sum: usize| &combinations[cells * (MAX_SUM + 1) + sum];
+        assert_eq!(of(2, 3), &[0b11]);
+        assert_eq!(of(2, 17), &[0b1_1000_0000]);
+        assert_eq!(of(3, 7), &[0b1011]);
+        assert_eq!(of(2, 10), &[0b10_1000, 0b100_0100, 0b1000_0010, 0b1_0000_0001]);
+        assert_eq!(of(9, 45), &[ALL]);
+        assert!(of(2, 2).is_empty() && of(3, 25).is_empty());
+        assert_eq!(combinations.iter().map(Vec::len).sum::<usize>(), 512);
+    }
+
+    #[test]
+    fn solves_a_killer_to_its_only_solution() {
+        let solver = SolverKiller::new(Killer::parse(LINE).unwrap(), 2);
+        match solve(&solver, EMPTY) {
+            Outcome::Solved(info) => {
+                assert_eq!(&info.sudoku.grid[..], SOLUTION);
+                assert_eq!(info.solutions, 1);
+            }
+            _ => panic!("expected a solution"),
+        }
+    }
+
+    #[test]
+    fn givens_must_fit_the_cages() {
+        let solver = SolverKiller::new(Killer::parse(LINE).unwrap(), 1);
+        let mut grid = EMPTY.to_vec();
+        grid[0] = b'5';
+        assert!(matches!(solve(&solver, &grid), Outcome::Solved(info) if &info.sudoku.grid[..] == SOLUTION));
+        // Cage A is r1c1 r1c2 r2c1 adding up to 14; 5, 3 and 7 complete it with 15
+        grid[1] = b'3';
+        grid[9] = b'7';
+        assert!(matches!(solve(&solver, &grid), Outcome::NoSolution));
+        grid[9] = b'6';
+        assert!(matches!(solve(&solver, &grid), Outcome::Solved(_)));
+    }
+}
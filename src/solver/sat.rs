@@ -0,0 +1,270 @@
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::sat::{blocking_clause, decode, parse_model, Cnf, Encoder, Encoding};
+use crate::solver::Solver;
+use crate::sudoku::{Puzzle, Sudoku};
+
+use super::{Budget, Outcome, SolutionInfo};
+
+/// SAT solvers looked for on `PATH` by `SolverSat::find`, in order of preference.
+const KNOWN_SOLVERS: [&str; 5] = ["kissat", "cadical", "cryptominisat5", "minisat", "glucose"];
+
+/// How often to check on a SAT solver that has a deadline.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Numbers the temporary directories of runs in this process.
+static NEXT_RUN: AtomicUsize = AtomicUsize::new(0);
+
+/// A new directory in the temp dir for one run's files, readable only by us
+/// on Unix. Creating it fails rather than reuse anything already there, so
+/// nobody else can plant files or symlinks where the CNF and model go.
+fn private_dir() -> io::Result<PathBuf> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    loop {
+        let dir = env::temp_dir().join(format!(
+            "oronsay-{}-{}",
+            process::id(),
+            NEXT_RUN.fetch_add(1, Ordering::Relaxed)
+        ));
+        match builder.create(&dir) {
+            Ok(()) => return Ok(dir),
+            // Left behind by an earlier process with our pid, or planted
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+enum Answer {
+    Model(Vec<i32>),
+    Unsatisfiable,
+    TimedOut,
+}
+
+/// Solves by writing the puzzle as CNF and running an external SAT solver.
+///
+/// MiniSat and Glucose are given a result file to write; any other binary is
+/// expected to print its answer in the SAT competition format. Every run
+/// after the first blocks the solutions found so far, so counting up to the
+/// limit takes one run per solution plus one. The budget's node limit caps
+/// the number of runs and its timeout is shared by all of them. A binary that
+/// cannot be run or whose answer cannot be read counts as giving up.
+#[derive(Clone)]
+pub struct SolverSat {
+    binary: PathBuf,
+    encoder: Encoder,
+    rules: Arc<Cnf>,
+    limit: usize,
+}
+
+impl SolverSat {
+    pub fn new(binary: impl Into<PathBuf>, limit: usize) -> Self {
+        let encoder = Encoder::new(Encoding::Extended);
+        Self {
+            binary: binary.into(),
+            rules: Arc::new(encoder.rules()),
+            encoder,
+            limit,
+        }
+    }
+
+    /// The first of kissat, CaDiCaL, CryptoMiniSat, MiniSat and Glucose found on `PATH`.
+    pub fn find(limit: usize) -> Option<Self> {
+        let paths = env::var_os("PATH")?;
+        KNOWN_SOLVERS.iter().find_map(|name| {
+            env::split_paths(&paths)
+                .map(|dir| dir.join(name))
+                .find(|path| path.is_file())
+                .map(|path| Self::new(path, limit))
+        })
+    }
+
+    /// Encodes puzzles with `encoder` instead of the extended encoding.
+    pub fn encoder(mut self, encoder: Encoder) -> Self {
+        self.rules = Arc::new(encoder.rules());
+        self.encoder = encoder;
+        self
+    }
+
+    pub fn binary(&self) -> &Path {
+        &self.binary
+    }
+
+    fn writes_result_file(&self) -> bool {
+        let name = self.binary.file_stem().unwrap_or_default().to_string_lossy();
+        name.starts_with("minisat") || name.starts_with("glucose")
+    }
+
+    fn run(&self, cnf: &Cnf, deadline: Option<Instant>) -> io::Result<Answer> {
+        let dir = private_dir()?;
+        let (input, output) = (dir.join("puzzle.cnf"), dir.join("model.out"));
+        let answer = File::create_new(&input)
+            .and_then(|file| cnf.write_dimacs(io::BufWriter::new(file)))
+            .and_then(|()| self.run_files(&input, &output, deadline));
+        let _ = fs::remove_dir_all(&dir);
+        answer
+    }
+
+    fn run_files(&self, input: &Path, output: &Path, deadline: Option<Instant>) -> io::Result<Answer> {
+        let mut command = Command::new(&self.binary);
+        command.arg(input).stdin(Stdio::null()).stderr(Stdio::null());
+        match self.writes_result_file() {
+            true => command.arg(output).stdout(Stdio::null()),
+            false => command.stdout(File::create_new(output)?),
+        };
+
+        let mut child = command.spawn()?;
+        match deadline {
+            Some(deadline) => loop {
+                if child.try_wait()?.is_some() {
+                    break;
+                }
+                if Instant::now() >= deadline {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Ok(Answer::TimedOut);
+                }
+                thread::sleep(POLL_INTERVAL);
+            },
+            None => {
+                child.wait()?;
+            }
+        }
+
+        let answer = parse_model(&fs::read_to_string(output)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        Ok(match answer {
+            Some(model) => Answer::Model(model),
+            None => Answer::Unsatisfiable,
+        })
+    }
+
+    /// Whether `sudoku` keeps the constraints, which the encoding may only cover in part.
+    fn keeps_constraints(&self, sudoku: &Sudoku) -> bool {
+        let values = sudoku.grid.map(|digit| digit - b'0');
+        self.encoder
+            .constraints_ref()
+            .iter()
+            .all(|constraint| constraint.is_satisfied(&values))
+    }
+}
+
+impl Solver for SolverSat {
+    type State = ();
+
+    fn make_state(&self) -> Self::State {}
+
+    fn solve_within(&self, puzzle: &Puzzle, _state: &mut Self::State, budget: &Budget) -> Outcome {
+        let deadline = budget.timeout.map(|timeout| Instant::now() + timeout);
+        let max_runs = budget.max_nodes.unwrap_or(usize::MAX);
+        let mut cnf = Cnf::clone(&self.rules);
+        cnf.add_givens(puzzle);
+
+        let (mut runs, mut solutions, mut first) = (0, 0, None);
+        while solutions < self.limit {
+            if runs == max_runs {
+                return Outcome::GaveUp {
+                    nodes: runs,
+                    guesses: 0,
+                };
+            }
+            runs += 1;
+            let sudoku = match self.run(&cnf, deadline) {
+                Ok(Answer::Model(model)) => decode(&model),
+                Ok(Answer::Unsatisfiable) => break,
+                Ok(Answer::TimedOut) | Err(_) => {
+                    return Outcome::GaveUp {
+                        nodes: runs,
+                        guesses: 0,
+                    }
+                }
+            };
+            if sudoku.grid.contains(&b'.') {
+                return Outcome::GaveUp {
+                    nodes: runs,
+                    guesses: 0,
+                };
+            }
+
+            if self.keeps_constraints(&sudoku) {
+                solutions += 1;
+                first.get_or_insert(sudoku);
+            }
+            let clause = blocking_clause(puzzle, &sudoku);
+            if clause.is_empty() {
+                break;
+            }
+            cnf.add_clause(clause);
+        }
+
+        match first {
+            Some(sudoku) => Outcome::Solved(SolutionInfo {
+                sudoku,
+                guesses: 0,
+                solutions,
+            }),
+            None => Outcome::NoSolution,
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    /// Runs `script` as a SAT solver named `name` on an empty grid.
+    fn run_fake(name: &str, script: &str) -> Outcome {
+        let dir = private_dir().unwrap();
+        let binary = dir.join(name);
+        let mut file = File::create_new(&binary).unwrap();
+        writeln!(file, "#!/bin/sh\n{}", script).unwrap();
+        drop(file);
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o700)).unwrap();
+
+        let solver = SolverSat::new(&binary, 2);
+        let outcome = solver.solve_within(&Puzzle::new(&[b'.'; 81]), &mut (), &Budget::unlimited());
+        fs::remove_dir_all(&dir).unwrap();
+        outcome
+    }
+
+    #[test]
+    fn reads_the_answer_from_standard_output() {
+        let outcome = run_fake("fake-sat", r#"grep -q '^p cnf 729 ' "$1" && echo 's UNSATISFIABLE'"#);
+        assert!(matches!(outcome, Outcome::NoSolution));
+    }
+
+    #[test]
+    fn reads_the_answer_from_a_minisat_result_file() {
+        let outcome = run_fake("minisat", r#"test -s "$1" && test ! -e "$2" && echo UNSAT > "$2""#);
+        assert!(matches!(outcome, Outcome::NoSolution));
+    }
+
+    #[test]
+    fn gives_up_on_output_it_cannot_read() {
+        let outcome = run_fake("fake-sat", "echo nonsense");
+        assert!(matches!(outcome, Outcome::GaveUp { nodes: 1, .. }));
+    }
+
+    #[test]
+    fn private_dirs_are_new_and_closed_to_others() {
+        let (first, second) = (private_dir().unwrap(), private_dir().unwrap());
+        assert_ne!(first, second);
+        for dir in [first, second] {
+            assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+            fs::remove_dir(&dir).unwrap();
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use alloc::{vec, vec::Vec};
+
+use super::{Budget, Meter};
+
+type Bits = u16;
+const ALL: Bits = 0x1ff;
+
+/// Any number of cells grouped into units of nine that each hold every digit once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Units {
+    units: Vec<[usize; 9]>,
+    /// For each cell, indices into `units`.
+    units_of: Vec<Vec<usize>>,
+}
+
+impl Units {
+    pub(crate) fn new(num_cells: usize, units: Vec<[usize; 9]>) -> Self {
+        let mut units_of = vec![Vec::new(); num_cells];
+        for (index, unit) in units.iter().enumerate() {
+            for &cell in unit {
+                units_of[cell].push(index);
+            }
+        }
+        Self { units, units_of }
+    }
+
+    pub(crate) fn num_cells(&self) -> usize {
+        self.units_of.len()
+    }
+}
+
+pub(crate) enum UnitOutcome {
+    /// The first solution as digits `1..=9`, and how many were found up to the limit.
+    Solved {
+        values: Vec<u8>,
+        guesses: usize,
+        solutions: usize,
+    },
+    NoSolution,
+    GaveUp {
+        nodes: usize,
+        guesses: usize,
+    },
+}
+
+/// Backtracking search with the most-constrained-cell heuristic over `Units`.
+pub(crate) struct UnitSearch<'a> {
+    units: &'a Units,
+    free: Vec<Bits>,
+    values: Vec<u8>,
+    todo: Vec<usize>,
+    limit: usize,
+    guesses: usize,
+    solutions: usize,
+    first: Option<Vec<u8>>,
+    meter: Meter,
+}
+
+impl<'a> UnitSearch<'a> {
+    pub(crate) fn new(units: &'a Units, limit: usize) -> Self {
+        Self {
+            units,
+            free: vec![ALL; units.units.len()],
+            values: vec![0; units.num_cells()],
+            todo: Vec::new(),
+            limit,
+            guesses: 0,
+            solutions: 0,
+            first: None,
+            meter: Meter::default(),
+        }
+    }
+
+    fn candidates(&self, cell: usize) -> Bits {
+        self.units.units_of[cell]
+            .iter()
+            .fold(ALL, |candidates, &unit| candidates & self.free[unit])
+    }
+
+    fn toggle(&mut self, cell: usize, bit: Bits) {
+        for &unit in &self.units.units_of[cell] {
+            self.free[unit] ^= bit;
+        }
+    }
+
+    /// Solves from `givens`, digits `1..=9` with `0` for an empty cell.
+    pub(crate) fn run(mut self, givens: &[u8], budget: &Budget) -> UnitOutcome {
+        for (cell, &value) in givens.iter().enumerate() {
+            if value == 0 {
+                self.todo.push(cell);
+                continue;
+            }
+            let bit = 1 << (value - 1);
+            if self.candidates(cell) & bit == 0 {
+                return UnitOutcome::NoSolution;
+            }
+            self.toggle(cell, bit);
+            self.values[cell] = value;
+        }
+
+        self.meter.start(budget);
+        match self.todo.is_empty() {
+            true => {
+                self.solutions = 1;
+                self.first = Some(self.values.clone());
+            }
+            false => self.satisfy(0),
+        }
+
+        if self.meter.gave_up {
+            UnitOutcome::GaveUp {
+                nodes: self.meter.nodes,
+                guesses: self.guesses,
+            }
+        } else if let Some(values) = self.first {
+            UnitOutcome::Solved {
+                values,
+                guesses: self.guesses,
+                solutions: self.solutions,
+            }
+        } else {
+            UnitOutcome::NoSolution
+        }
+    }
+
+    fn satisfy(&mut self, todo_index: usize) {
+        if self.meter.tick() {
+            return;
+        }
+        let best = (todo_index..self.todo.len())
+            .min_by_key(|&index| self.candidates(self.todo[index]).count_ones())
+            .unwrap_or(todo_index);
+        self.todo.swap(todo_index, best);
+
+        let cell = self.todo[todo_index];
+        let mut candidates = self.candidates(cell);
+        while candidates != 0 {
+            let bit = candidates & candidates.wrapping_neg();
+            candidates ^= bit;
+            if candidates != 0 {
+                self.guesses += 1;
+            }
+
+            self.toggle(cell, bit);
+            self.values[cell] = bit.trailing_zeros() as u8 + 1;
+            if todo_index + 1 < self.todo.len() {
+                self.satisfy(todo_index + 1);
+            } else {
+                self.solutions += 1;
+                if self.solutions == 1 {
+                    self.first = Some(self.values.clone());
+                }
+            }
+            self.values[cell] = 0;
+            self.toggle(cell, bit);
+
+            if self.solutions == self.limit || self.meter.gave_up {
+                return;
+            }
+        }
+    }
+}
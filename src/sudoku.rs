@@ -1,3 +1,18 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use core::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alphabet::Alphabet,
+    candidates::{box_of, col_of, row_of},
+    error::{Error, Result},
+};
+
 pub const N_CELLS: usize = 81;
 
 pub struct Puzzle<'a> {
@@ -16,7 +31,10 @@ impl<'a> Puzzle<'a> {
     }
 }
 
-#[derive(Clone, Copy)]
+/// Serialised as its 81 character string, `.` for empty.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
 pub struct Sudoku {
     pub grid: [u8; N_CELLS],
 }
@@ -28,19 +46,34 @@ impl Sudoku {
 
     pub fn clean(&self) -> Sudoku {
         let mut new_grid = [0u8; N_CELLS];
-        for i in 0..N_CELLS {
-            new_grid[i] = match self.grid[i] {
-                b'1'..=b'9' => self.grid[i],
+        for (new, &old) in new_grid.iter_mut().zip(self.grid.iter()) {
+            *new = match old {
+                b'1'..=b'9' => old,
                 _ => b'.',
             };
         }
         Sudoku::new(new_grid)
     }
 
-    // need a function to convert the grid to a string
-    pub fn to_string(&self) -> String {
-        let clean = self.clean();
-        clean.grid.iter().map(|&c| c as char).collect()
+    /// Whether this is a complete, valid grid that keeps every given of `puzzle`.
+    pub fn solves(&self, puzzle: &Sudoku) -> bool {
+        let mut rows = [0u16; 9];
+        let mut cols = [0u16; 9];
+        let mut subs = [0u16; 9];
+        for (cell, (&value, &given)) in self.grid.iter().zip(puzzle.grid.iter()).enumerate() {
+            if !value.is_ascii_digit() || value == b'0' {
+                return false;
+            }
+            if given.is_ascii_digit() && given != b'0' && given != value {
+                return false;
+            }
+            let (row, col, sub) = (row_of(cell), col_of(cell), box_of(cell));
+            let bit = 1 << (value - b'1');
+            rows[row] |= bit;
+            cols[col] |= bit;
+            subs[sub] |= bit;
+        }
+        rows.iter().chain(&cols).chain(&subs).all(|&unit| unit == 0x1ff)
     }
 
     pub fn pretty(&self) -> String {
@@ -66,3 +99,60 @@ impl Sudoku {
         result
     }
 }
+
+/// The 81 character string, `.` for empty.
+impl fmt::Display for Sudoku {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let clean = self.clean();
+        f.write_str(&clean.grid.iter().map(|&c| c as char).collect::<String>())
+    }
+}
+
+impl fmt::Debug for Sudoku {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Sudoku").field(&self.to_string()).finish()
+    }
+}
+
+impl From<Sudoku> for String {
+    fn from(sudoku: Sudoku) -> Self {
+        sudoku.to_string()
+    }
+}
+
+/// Exactly 81 cells, `1`-`9` or `.` and `0` for empty, as `Alphabet::default`
+/// allows; see `FromStr` for other layouts.
+impl TryFrom<String> for Sudoku {
+    type Error = Error;
+
+    fn try_from(text: String) -> Result<Self> {
+        let grid: [u8; N_CELLS] = text.as_bytes().try_into().map_err(|_| {
+            Error::Format(format!("puzzle is {} characters long, expected {}", text.len(), N_CELLS))
+        })?;
+        Alphabet::default().validate(1, &grid)?;
+        Ok(Sudoku::new(grid).clean())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_from_string_accepts_digits_dots_and_zeros() {
+        let text = format!("{}0{}", "1".repeat(40), ".".repeat(40));
+        let sudoku = Sudoku::try_from(text).unwrap();
+        assert_eq!(sudoku.grid[40], b'.');
+        assert_eq!(sudoku.grid[0], b'1');
+    }
+
+    #[test]
+    fn try_from_string_names_the_first_bad_cell() {
+        let text = format!("{}x{}", ".".repeat(10), ".".repeat(70));
+        match Sudoku::try_from(text) {
+            Err(Error::InvalidCharacter { line, column, byte }) => assert_eq!((line, column, byte), (1, 11, b'x')),
+            other => panic!("expected an invalid character, got {:?}", other.map(|sudoku| sudoku.to_string())),
+        }
+        assert!(matches!(Sudoku::try_from(".".repeat(80)), Err(Error::Format(_))));
+    }
+}
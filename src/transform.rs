@@ -0,0 +1,233 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::sudoku::{Sudoku, N_CELLS};
+
+/// A validity-preserving rearrangement of a grid: a permutation of the cells
+/// followed by a relabelling of the digits. Transforms compose with `then`
+/// and apply equally to puzzles and their solutions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transform {
+    /// Output cell `i` takes the value of input cell `cells[i]`.
+    cells: [u8; N_CELLS],
+    /// Digit `d` becomes `digits[d - 1]`.
+    digits: [u8; 9],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        let mut cells = [0; N_CELLS];
+        for (i, cell) in cells.iter_mut().enumerate() {
+            *cell = i as u8;
+        }
+        let mut digits = [0; 9];
+        for (i, digit) in digits.iter_mut().enumerate() {
+            *digit = b'1' + i as u8;
+        }
+        Self { cells, digits }
+    }
+
+    /// Builds a cell permutation from `source(row, col)`, the input cell for each output cell.
+    fn from_cells(source: impl Fn(usize, usize) -> (usize, usize)) -> Self {
+        let mut transform = Self::identity();
+        for (i, cell) in transform.cells.iter_mut().enumerate() {
+            let (row, col) = source(i / 9, i % 9);
+            *cell = (row * 9 + col) as u8;
+        }
+        transform
+    }
+
+    pub fn transpose() -> Self {
+        Self::from_cells(|row, col| (col, row))
+    }
+
+    /// Quarter turn clockwise.
+    pub fn rotate() -> Self {
+        Self::from_cells(|row, col| (8 - col, row))
+    }
+
+    /// Mirror left to right.
+    pub fn reflect() -> Self {
+        Self::from_cells(|row, col| (row, 8 - col))
+    }
+
+    /// Reorders the rows of `band`, so row `i` of the band comes from row `order[i]`.
+    /// Returns `None` unless `band` is below 3 and `order` is a permutation of `0..3`.
+    pub fn permute_rows(band: usize, order: [usize; 3]) -> Option<Self> {
+        (band < 3 && is_order(order)).then(|| {
+            Self::from_cells(|row, col| match row / 3 == band {
+                true => (band * 3 + order[row % 3], col),
+                false => (row, col),
+            })
+        })
+    }
+
+    /// Reorders the columns of `stack`, so column `i` of the stack comes from column `order[i]`.
+    /// Returns `None` unless `stack` is below 3 and `order` is a permutation of `0..3`.
+    pub fn permute_cols(stack: usize, order: [usize; 3]) -> Option<Self> {
+        (stack < 3 && is_order(order)).then(|| {
+            Self::from_cells(|row, col| match col / 3 == stack {
+                true => (row, stack * 3 + order[col % 3]),
+                false => (row, col),
+            })
+        })
+    }
+
+    /// Reorders the bands, so band `i` comes from band `order[i]`.
+    /// Returns `None` unless `order` is a permutation of `0..3`.
+    pub fn permute_bands(order: [usize; 3]) -> Option<Self> {
+        is_order(order).then(|| Self::from_cells(|row, col| (order[row / 3] * 3 + row % 3, col)))
+    }
+
+    /// Reorders the stacks, so stack `i` comes from stack `order[i]`.
+    /// Returns `None` unless `order` is a permutation of `0..3`.
+    pub fn permute_stacks(order: [usize; 3]) -> Option<Self> {
+        is_order(order).then(|| Self::from_cells(|row, col| (row, order[col / 3] * 3 + col % 3)))
+    }
+
+    /// Relabels digit `d` as `digits[d - 1]`, given as ASCII `'1'..='9'`.
+    /// Returns `None` unless `digits` is a permutation of the nine digits.
+    pub fn relabel(digits: [u8; 9]) -> Option<Self> {
+        let mut seen = 0u16;
+        for &digit in &digits {
+            if !(b'1'..=b'9').contains(&digit) {
+                return None;
+            }
+            seen |= 1 << (digit - b'1');
+        }
+        (seen == 0x1ff).then(|| Self {
+            digits,
+            ..Self::identity()
+        })
+    }
+
+    /// A uniformly random element of the group generated by the transforms above.
+    pub fn random(rng: &mut impl Rng) -> Self {
+        fn order(rng: &mut impl Rng) -> [usize; 3] {
+            let mut order = [0, 1, 2];
+            order.shuffle(rng);
+            order
+        }
+
+        let mut transform = match rng.random() {
+            true => Self::transpose(),
+            false => Self::identity(),
+        };
+        let permutations = [Self::permute_bands(order(rng)), Self::permute_stacks(order(rng))]
+            .into_iter()
+            .chain((0..3).flat_map(|i| [Self::permute_rows(i, order(rng)), Self::permute_cols(i, order(rng))]));
+        for permutation in permutations {
+            transform = transform.then(&permutation.expect("shuffles of 0..3 are permutations"));
+        }
+        let mut digits = Self::identity().digits;
+        digits.shuffle(rng);
+        transform.digits = digits;
+        transform
+    }
+
+    /// The transform that applies `self` and then `next`.
+    pub fn then(&self, next: &Transform) -> Self {
+        let mut composed = Self::identity();
+        for (cell, &source) in composed.cells.iter_mut().zip(next.cells.iter()) {
+            *cell = self.cells[source as usize];
+        }
+        for (digit, &first) in composed.digits.iter_mut().zip(self.digits.iter()) {
+            *digit = next.digits[(first - b'1') as usize];
+        }
+        composed
+    }
+
+    pub fn inverse(&self) -> Self {
+        let mut inverse = Self::identity();
+        for (i, &source) in self.cells.iter().enumerate() {
+            inverse.cells[source as usize] = i as u8;
+        }
+        for (i, &digit) in self.digits.iter().enumerate() {
+            inverse.digits[(digit - b'1') as usize] = b'1' + i as u8;
+        }
+        inverse
+    }
+
+    /// Applies the transform; cells that are not digits are moved but left as they are.
+    pub fn apply(&self, sudoku: &Sudoku) -> Sudoku {
+        let mut grid = [0; N_CELLS];
+        for (value, &source) in grid.iter_mut().zip(self.cells.iter()) {
+            *value = match sudoku.grid[source as usize] {
+                digit @ b'1'..=b'9' => self.digits[(digit - b'1') as usize],
+                other => other,
+            };
+        }
+        Sudoku::new(grid)
+    }
+}
+
+/// Whether `order` is a permutation of `0..3`.
+fn is_order(order: [usize; 3]) -> bool {
+    order.iter().fold(0u8, |seen, &i| seen | (1 << i.min(3))) == 0b111
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    const PUZZLE: &[u8] = b"53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+    const SOLUTION: &[u8] = b"534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+    fn sudoku(grid: &[u8]) -> Sudoku {
+        Sudoku::new(grid.try_into().unwrap())
+    }
+
+    #[test]
+    fn permutations_must_be_of_three_rows() {
+        assert!(Transform::permute_rows(2, [2, 0, 1]).is_some());
+        assert!(Transform::permute_rows(3, [0, 1, 2]).is_none());
+        assert!(Transform::permute_cols(0, [0, 0, 1]).is_none());
+        assert!(Transform::permute_cols(1, [0, 1, 3]).is_none());
+        assert!(Transform::permute_bands([1, 2, 0]).is_some());
+        assert!(Transform::permute_bands([1, 1, 1]).is_none());
+        assert!(Transform::permute_stacks([usize::MAX, 0, 1]).is_none());
+        assert!(Transform::relabel(*b"123456788").is_none());
+    }
+
+    #[test]
+    fn inverse_undoes_apply() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let (puzzle, solution) = (sudoku(PUZZLE), sudoku(SOLUTION));
+        for _ in 0..50 {
+            let transform = Transform::random(&mut rng);
+            let moved = transform.apply(&puzzle);
+            assert_eq!(transform.inverse().apply(&moved), puzzle);
+            assert_eq!(transform.inverse().apply(&transform.apply(&solution)), solution);
+            assert_eq!(transform.then(&transform.inverse()), Transform::identity());
+        }
+    }
+
+    #[test]
+    fn transformed_solutions_stay_valid() {
+        let mut rng = ChaCha8Rng::seed_from_u64(11);
+        let (puzzle, solution) = (sudoku(PUZZLE), sudoku(SOLUTION));
+        assert!(solution.solves(&puzzle));
+        let fixed = [
+            Transform::transpose(),
+            Transform::rotate(),
+            Transform::reflect(),
+            Transform::permute_rows(1, [2, 1, 0]).unwrap(),
+            Transform::permute_cols(2, [1, 2, 0]).unwrap(),
+            Transform::permute_bands([2, 0, 1]).unwrap(),
+            Transform::permute_stacks([1, 0, 2]).unwrap(),
+            Transform::relabel(*b"987654321").unwrap(),
+        ];
+        let random = (0..50).map(|_| Transform::random(&mut rng));
+        for transform in fixed.into_iter().chain(random) {
+            assert!(transform.apply(&solution).solves(&transform.apply(&puzzle)));
+        }
+    }
+}
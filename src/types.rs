@@ -1,20 +1,54 @@
+#[cfg(feature = "std")]
 use memmap2::Mmap;
-use std::{sync::Arc, time::Duration};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use alloc::{sync::Arc, vec::Vec};
+#[cfg(feature = "std")]
+use core::ops::Deref;
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
 
+/// Puzzle data shared between the reader and workers.
+#[cfg(feature = "std")]
+pub enum Source {
+    Mmap(Mmap),
+    Bytes(Vec<u8>),
+}
+
+#[cfg(feature = "std")]
+impl Deref for Source {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Source::Mmap(mmap) => mmap,
+            Source::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 pub struct PuzzleChunk {
     pub id: usize,
+    /// 1-based line number of the first puzzle in the chunk.
+    pub first_line: usize,
     pub start: usize,
     pub end: usize,
-    pub mmap: Arc<Mmap>,
+    pub source: Arc<Source>,
 }
 
-#[derive(Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ChunkStats {
     pub chunks: usize,
     pub puzzles: usize,
     pub solutions: usize,
     pub no_guesses: usize,
     pub guesses: usize,
+    /// Puzzles abandoned because they ran out of budget.
+    pub gave_up: usize,
     pub elapsed: Duration,
 }
 
@@ -25,12 +59,33 @@ impl ChunkStats {
         self.solutions += other.solutions;
         self.no_guesses += other.no_guesses;
         self.guesses += other.guesses;
+        self.gave_up += other.gave_up;
         self.elapsed += other.elapsed;
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SolvedChunk {
     pub id: usize,
     pub data: Vec<u8>,
     pub stats: ChunkStats,
 }
+
+/// Shared flag used to stop the reader and workers early.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,324 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+use crate::{
+    candidates::parse_cell,
+    constraint::{AntiKing, AntiKnight, Arrow, Constraint, Dot, Kropki, NonConsecutive, Thermo},
+    error::{Error, Result},
+    layout::Layout,
+    sat::{Encoder, Encoding},
+    solver::SolverBasic,
+    sudoku::{Puzzle, Sudoku, N_CELLS},
+};
+
+/// One entry of the `constraints` list, cells in `r1c1` notation.
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields))]
+enum ConstraintSpec {
+    AntiKnight,
+    AntiKing,
+    NonConsecutive,
+    Thermo { cells: Vec<String> },
+    Arrow { circle: String, cells: Vec<String> },
+    Kropki { dot: DotSpec, cells: [String; 2] },
+}
+
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+enum DotSpec {
+    White,
+    Black,
+}
+
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+struct Description {
+    grid: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    variant: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    constraints: Vec<ConstraintSpec>,
+}
+
+fn cell(name: &str) -> Result<usize> {
+    parse_cell(name).ok_or_else(|| Error::Format(format!("'{}' is not a cell like r1c1", name)))
+}
+
+fn cells(names: &[impl AsRef<str>]) -> Result<Vec<usize>> {
+    names.iter().map(|name| cell(name.as_ref())).collect()
+}
+
+impl ConstraintSpec {
+    fn build(&self) -> Result<Arc<dyn Constraint>> {
+        Ok(match self {
+            ConstraintSpec::AntiKnight => Arc::new(AntiKnight),
+            ConstraintSpec::AntiKing => Arc::new(AntiKing),
+            ConstraintSpec::NonConsecutive => Arc::new(NonConsecutive),
+            ConstraintSpec::Thermo { cells: names } => Arc::new(Thermo {
+                cells: cells(names)?,
+            }),
+            ConstraintSpec::Arrow { circle, cells: names } => Arc::new(Arrow {
+                circle: cell(circle)?,
+                cells: cells(names)?,
+            }),
+            ConstraintSpec::Kropki { dot, cells: [a, b] } => Arc::new(Kropki {
+                cells: [cell(a)?, cell(b)?],
+                dot: match dot {
+                    DotSpec::White => Dot::White,
+                    DotSpec::Black => Dot::Black,
+                },
+            }),
+        })
+    }
+
+    /// A text line: the JSON `type` followed by its fields, e.g.
+    /// `thermo r1c1 r1c2`, `arrow r1c1 r1c2 r1c3` (circle first) or
+    /// `kropki black r1c1 r1c2`.
+    fn parse(line: &str) -> Result<Self> {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next().unwrap_or_default();
+        let rest = fields.map(str::to_string).collect::<Vec<_>>();
+        let spec = match (kind, rest.as_slice()) {
+            ("anti-knight", []) => ConstraintSpec::AntiKnight,
+            ("anti-king", []) => ConstraintSpec::AntiKing,
+            ("non-consecutive", []) => ConstraintSpec::NonConsecutive,
+            ("thermo", [_, _, ..]) => ConstraintSpec::Thermo { cells: rest },
+            ("arrow", [circle, cells @ ..]) if !cells.is_empty() => ConstraintSpec::Arrow {
+                circle: circle.clone(),
+                cells: cells.to_vec(),
+            },
+            ("kropki", [dot, a, b]) => ConstraintSpec::Kropki {
+                dot: match dot.as_str() {
+                    "white" => DotSpec::White,
+                    "black" => DotSpec::Black,
+                    _ => return Err(Error::Format(format!("unknown kropki dot '{}'", dot))),
+                },
+                cells: [a.clone(), b.clone()],
+            },
+            _ => return Err(Error::Format(format!("invalid constraint '{}'", line))),
+        };
+        Ok(spec)
+    }
+}
+
+/// A puzzle with its variant rules: the givens, a region layout and any
+/// further constraints.
+///
+/// Described in JSON as
+///
+/// ```json
+/// {
+///   "grid": "<81 characters>",
+///   "variant": "x",
+///   "constraints": [
+///     { "type": "anti-knight" },
+///     { "type": "thermo", "cells": ["r1c1", "r1c2", "r1c3"] },
+///     { "type": "arrow", "circle": "r5c5", "cells": ["r5c6", "r5c7"] },
+///     { "type": "kropki", "dot": "white", "cells": ["r9c1", "r9c2"] }
+///   ]
+/// }
+/// ```
+///
+/// or in text, one `key value` line each for the grid and variant and one
+/// line per constraint:
+///
+/// ```text
+/// grid <81 characters>
+/// variant x
+/// anti-knight
+/// thermo r1c1 r1c2 r1c3
+/// arrow r5c5 r5c6 r5c7
+/// kropki white r9c1 r9c2
+/// ```
+#[derive(Clone)]
+pub struct VariantPuzzle {
+    pub grid: Sudoku,
+    pub layout: Layout,
+    pub constraints: Vec<Arc<dyn Constraint>>,
+}
+
+impl VariantPuzzle {
+    fn from_description(description: Description) -> Result<Self> {
+        let grid = description.grid.trim().as_bytes();
+        if grid.len() != N_CELLS {
+            return Err(Error::Format(format!(
+                "grid is {} characters long, expected {}",
+                grid.len(),
+                N_CELLS
+            )));
+        }
+        Ok(Self {
+            grid: Puzzle::new(grid).sudoku(),
+            layout: match description.variant {
+                Some(variant) => Layout::parse(&variant)?,
+                None => Layout::standard(),
+            },
+            constraints: description
+                .constraints
+                .iter()
+                .map(ConstraintSpec::build)
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Parses JSON if the text starts with `{`, the text form otherwise.
+    pub fn parse(text: &str) -> Result<Self> {
+        match text.trim_start().starts_with('{') {
+            true => Self::from_json(text),
+            false => Self::parse_text(text),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(text: &str) -> Result<Self> {
+        let description = serde_json::from_str(text)
+            .map_err(|err| Error::Format(format!("puzzle description: {}", err)))?;
+        Self::from_description(description)
+    }
+
+    #[cfg(not(feature = "serde"))]
+    pub fn from_json(_text: &str) -> Result<Self> {
+        Err(Error::Format("JSON puzzle descriptions need the serde feature".into()))
+    }
+
+    /// Parses the text form. Blank lines and lines starting with `#` are skipped.
+    pub fn parse_text(text: &str) -> Result<Self> {
+        let mut description = Description {
+            grid: String::new(),
+            variant: None,
+            constraints: Vec::new(),
+        };
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once(char::is_whitespace) {
+                Some(("grid", grid)) => description.grid = grid.trim().to_string(),
+                Some(("variant", variant)) => description.variant = Some(variant.trim().to_string()),
+                _ => description.constraints.push(ConstraintSpec::parse(line)?),
+            }
+        }
+        Self::from_description(description)
+    }
+
+    /// A solver for these rules that stops after `limit` solutions.
+    pub fn solver(&self, limit: usize) -> SolverBasic {
+        SolverBasic::new(limit, true)
+            .layout(self.layout.clone())
+            .constraints(self.constraints.iter().cloned())
+    }
+
+    /// A CNF encoder for these rules.
+    pub fn encoder(&self, encoding: Encoding) -> Encoder {
+        Encoder::new(encoding)
+            .layout(self.layout.clone())
+            .constraints(self.constraints.iter().cloned())
+    }
+
+    /// Whether `sudoku` is a complete grid that keeps the givens, the layout
+    /// and every constraint.
+    pub fn is_solved_by(&self, sudoku: &Sudoku) -> bool {
+        let mut values = [0; N_CELLS];
+        for (value, (&digit, &given)) in values.iter_mut().zip(sudoku.grid.iter().zip(&self.grid.grid)) {
+            if (b'1'..=b'9').contains(&given) && given != digit {
+                return false;
+            }
+            *value = digit.wrapping_sub(b'0');
+        }
+        self.layout.is_solved_by(sudoku)
+            && self.constraints.iter().all(|constraint| constraint.is_satisfied(&values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::solver::Solver;
+
+    use super::*;
+
+    const GRID: &str = "4..7.....3.7...1.....2...8.2.....5.....4.....8.....9.....1.....7.95.861....9.....";
+    const SOLUTION: &[u8] = b"428761359397854126516239487261397548973485261854612973685123794739548612142976835";
+    const TEXT: &str = "
+        # Sudoku-X, anti-knight, with a dot and a thermometer
+        variant x
+        anti-knight
+        kropki black r1c1 r1c2
+        thermo r9c1 r8c2 r7c3
+    ";
+
+    fn assert_solves(puzzle: &VariantPuzzle) {
+        let solver = puzzle.solver(2);
+        let info = solver.solve(&Puzzle::new(&puzzle.grid.grid), &mut solver.make_state()).unwrap();
+        assert_eq!(info.solutions, 1);
+        assert_eq!(&info.sudoku.grid[..], SOLUTION);
+        assert!(puzzle.is_solved_by(&info.sudoku));
+    }
+
+    fn error(result: Result<VariantPuzzle>) -> String {
+        match result {
+            Err(Error::Format(message)) => message,
+            Err(err) => panic!("expected a format error, got {}", err),
+            Ok(_) => panic!("expected a format error"),
+        }
+    }
+
+    #[test]
+    fn solves_a_text_description() {
+        let puzzle = VariantPuzzle::parse(&format!("grid {}\n{}", GRID, TEXT)).unwrap();
+        assert_eq!(puzzle.layout.num_regions(), 2);
+        assert_eq!(puzzle.constraints.len(), 3);
+        assert_solves(&puzzle);
+
+        // Swapping two digits of a row breaks their columns
+        let mut wrong = Sudoku::new(SOLUTION.try_into().unwrap());
+        wrong.grid.swap(72, 74);
+        assert!(!puzzle.is_solved_by(&wrong));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn solves_a_json_description() {
+        let json = format!(
+            r#"{{
+                "grid": "{}",
+                "variant": "x",
+                "constraints": [
+                    {{ "type": "anti-knight" }},
+                    {{ "type": "kropki", "dot": "black", "cells": ["r1c1", "r1c2"] }},
+                    {{ "type": "thermo", "cells": ["r9c1", "r8c2", "r7c3"] }}
+                ]
+            }}"#,
+            GRID
+        );
+        assert_solves(&VariantPuzzle::parse(&json).unwrap());
+        let unknown = r#"{ "grid": "", "constraints": [{ "type": "sandwich" }] }"#;
+        assert!(error(VariantPuzzle::parse(unknown)).starts_with("puzzle description:"));
+    }
+
+    #[cfg(not(feature = "serde"))]
+    #[test]
+    fn json_needs_serde() {
+        assert!(error(VariantPuzzle::parse("{}")).contains("serde feature"));
+    }
+
+    #[test]
+    fn rejects_malformed_descriptions() {
+        let with = |line: &str| VariantPuzzle::parse_text(&format!("grid {}\n{}", GRID, line));
+        assert!(with("variant x").is_ok());
+        assert!(error(with("thermo r1c1")).contains("invalid constraint 'thermo r1c1'"));
+        assert!(error(with("arrow r1c1")).contains("invalid constraint"));
+        assert!(error(with("anti-king r1c1")).contains("invalid constraint"));
+        assert!(error(with("kropki grey r1c1 r1c2")).contains("unknown kropki dot 'grey'"));
+        assert!(error(with("thermo r1c1 r0c2")).contains("'r0c2' is not a cell"));
+        assert!(error(with("variant jigsaw:AAA")).contains("region map is 3 characters long"));
+        assert!(error(VariantPuzzle::parse_text("variant x")).contains("grid is 0 characters long"));
+        assert!(error(VariantPuzzle::parse_text(&format!("grid {}.", GRID))).contains("82 characters"));
+    }
+}
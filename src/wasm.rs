@@ -0,0 +1,130 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::str::FromStr;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    generate::generate as generate_puzzle,
+    hint::{hint as find_hint, Action, Hint},
+    solver::{Solver, SolverBasic},
+    sudoku::{Puzzle, Sudoku},
+};
+
+fn parse(puzzle: &str) -> Result<Sudoku, String> {
+    Sudoku::from_str(puzzle).map_err(|err| err.to_string())
+}
+
+/// The first solution of `puzzle`, or `undefined` if it has none. Throws if
+/// the puzzle cannot be read.
+#[wasm_bindgen]
+pub fn solve(puzzle: &str) -> Result<Option<String>, String> {
+    let sudoku = parse(puzzle)?;
+    let solver = SolverBasic::new(1, true);
+    Ok(solver
+        .solve(&Puzzle::new(&sudoku.grid), &mut solver.make_state())
+        .map(|solution| solution.sudoku.to_string()))
+}
+
+/// The next step for `current`, the `givens` plus the player's entries, as
+/// JSON: `{"kind": "step", "technique", "place": {"cell", "value"} or null,
+/// "eliminate": [[cell, value], ...], "because": [cell, ...]}`,
+/// `{"kind": "mistakes", "cells": [...]}`, `{"kind": "solved"}` or
+/// `{"kind": "not_unique"}`. Cells are numbered from 0 in reading order.
+#[wasm_bindgen]
+pub fn hint(givens: &str, current: &str) -> Result<String, String> {
+    let (givens, current) = (parse(givens)?, parse(current)?);
+    let solver = SolverBasic::new(2, true);
+    let answer = match find_hint(&solver, &mut solver.make_state(), &givens, &current) {
+        Hint::Step(step) => {
+            let (place, eliminate) = match step.action {
+                Action::Place { cell, value } => (json!({ "cell": cell, "value": value }), Vec::new()),
+                Action::Eliminate(eliminations) => (Value::Null, eliminations),
+            };
+            json!({
+                "kind": "step",
+                "technique": step.technique.name(),
+                "place": place,
+                "eliminate": eliminate,
+                "because": step.because,
+            })
+        }
+        Hint::Mistakes(cells) => json!({ "kind": "mistakes", "cells": cells }),
+        Hint::Solved => json!({ "kind": "solved" }),
+        Hint::NotUnique => json!({ "kind": "not_unique" }),
+    };
+    Ok(answer.to_string())
+}
+
+/// A random puzzle with a unique solution; the same `seed` gives the same puzzle.
+#[wasm_bindgen]
+pub fn generate(seed: u32) -> String {
+    generate_puzzle(&mut ChaCha8Rng::seed_from_u64(seed.into())).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &str = ".5....2....9..2.81.8..3.9...3.....5..7.6.1..851.8.......4.1...9.....8.......694..";
+
+    #[test]
+    fn solve_returns_the_solution_or_nothing() {
+        let solution = solve(PUZZLE).unwrap().unwrap();
+        let (solution, givens) = (parse(&solution).unwrap(), parse(PUZZLE).unwrap());
+        assert!(solution.solves(&givens));
+        assert_eq!(solve(&"1".repeat(81)).unwrap(), None);
+        assert!(solve("not a puzzle").is_err());
+    }
+
+    #[test]
+    fn hint_follows_the_player_to_the_end() {
+        let solution = solve(PUZZLE).unwrap().unwrap();
+        let mut current = parse(PUZZLE).unwrap();
+        let mut placed = 0;
+        loop {
+            let answer: Value = serde_json::from_str(&hint(PUZZLE, &current.to_string()).unwrap()).unwrap();
+            match answer["kind"].as_str().unwrap() {
+                "step" => {
+                    assert!(answer["technique"].is_string());
+                    if let Some(cell) = answer["place"]["cell"].as_u64() {
+                        current.grid[cell as usize] = b'0' + answer["place"]["value"].as_u64().unwrap() as u8;
+                        placed += 1;
+                    }
+                }
+                "solved" => break,
+                kind => panic!("unexpected {} hint", kind),
+            }
+        }
+        assert_eq!(current.to_string(), solution);
+        assert_eq!(placed, PUZZLE.bytes().filter(|&byte| byte == b'.').count());
+    }
+
+    #[test]
+    fn hint_points_out_mistakes_and_ambiguous_givens() {
+        let mut wrong = parse(PUZZLE).unwrap();
+        let cell = wrong.grid.iter().position(|&byte| byte == b'.').unwrap();
+        let solution = solve(PUZZLE).unwrap().unwrap();
+        wrong.grid[cell] = if solution.as_bytes()[cell] == b'1' { b'2' } else { b'1' };
+        let answer: Value = serde_json::from_str(&hint(PUZZLE, &wrong.to_string()).unwrap()).unwrap();
+        // Peers whose answer the wrong digit rules out are reported with it
+        assert_eq!(answer["kind"], "mistakes");
+        assert!(answer["cells"].as_array().unwrap().contains(&json!(cell)));
+
+        let empty = ".".repeat(81);
+        let answer: Value = serde_json::from_str(&hint(&empty, &empty).unwrap()).unwrap();
+        assert_eq!(answer, json!({ "kind": "not_unique" }));
+    }
+
+    #[test]
+    fn generate_is_deterministic_and_solvable() {
+        let puzzle = generate(7);
+        assert_eq!(generate(7), puzzle);
+        assert!(solve(&puzzle).unwrap().is_some());
+    }
+}
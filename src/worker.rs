@@ -3,20 +3,24 @@ use std::thread;
 use crossbeam::channel;
 
 use crate::{
-    solver::Solver,
+    alphabet::Alphabet,
+    error::{Error, Result},
+    solver::{Budget, Outcome, Solver},
     sudoku::{Puzzle, N_CELLS},
-    types::{ChunkStats, PuzzleChunk, SolvedChunk},
+    types::{CancelToken, ChunkStats, PuzzleChunk, SolvedChunk},
 };
 
 pub struct Worker<S: Solver> {
     solver: S,
     line_length: usize,
+    budget: Budget,
+    alphabet: Alphabet,
 }
 
 impl<S: Solver> Worker<S> {
-    fn process_chunk(&self, chunk: PuzzleChunk, state: &mut S::State) -> SolvedChunk {
+    fn process_chunk(&self, chunk: PuzzleChunk, state: &mut S::State) -> Result<SolvedChunk> {
         let start = std::time::Instant::now();
-        let data = &chunk.mmap[chunk.start..chunk.end];
+        let data = &chunk.source[chunk.start..chunk.end];
         let mut solved = SolvedChunk {
             id: chunk.id,
             data: Vec::with_capacity(data.len() * 3),
@@ -26,26 +30,38 @@ impl<S: Solver> Worker<S> {
         for puzzle_slice in data.chunks_exact(self.line_length) {
             let puzzle = Puzzle::new(&puzzle_slice[..N_CELLS]);
             solved.stats.puzzles += 1;
+            let line = chunk.first_line + solved.stats.puzzles - 1;
+            self.alphabet.validate(line, puzzle.grid)?;
 
-            solved.data.extend_from_slice(puzzle.grid.as_ref());
+            self.alphabet.write(puzzle.grid, &mut solved.data);
             solved.data.push(b',');
 
-            let solution = match self.solver.solve(&puzzle, state) {
-                Some(solution) => solution,
-                None => {
-                    let sudoku = puzzle.sudoku();
-                    panic!(
-                        "Failed to solve sudoku {}: {}\n{}",
-                        solved.stats.puzzles,
-                        sudoku.to_string(),
-                        sudoku.pretty()
-                    )
+            let solution = match self.solver.solve_within(&puzzle, state, &self.budget) {
+                Outcome::Solved(solution) => solution,
+                Outcome::NoSolution => {
+                    return Err(Error::Unsolvable {
+                        line,
+                        puzzle: puzzle.sudoku().to_string(),
+                    })
+                }
+                Outcome::GaveUp { guesses, .. } => {
+                    // Leave the unsolved cells blank so the line keeps its length
+                    let empty = self.alphabet.output_empty();
+                    solved.data.extend(puzzle.grid.iter().map(|&byte| match byte {
+                        b'1'..=b'9' => byte,
+                        _ => empty,
+                    }));
+                    solved.data.push(b'\n');
+                    solved.stats.gave_up += 1;
+                    solved.stats.guesses += guesses;
+                    continue;
                 }
             };
 
             solved.data.extend_from_slice(solution.sudoku.grid.as_ref());
             solved.data.push(b'\n');
 
+            solved.stats.solutions += 1;
             solved.stats.guesses += solution.guesses;
             if solution.guesses == 0 {
                 solved.stats.no_guesses += 1;
@@ -54,7 +70,7 @@ impl<S: Solver> Worker<S> {
 
         solved.stats.chunks += 1;
         solved.stats.elapsed = start.elapsed();
-        solved
+        Ok(solved)
     }
 
     pub fn spawn(
@@ -62,27 +78,50 @@ impl<S: Solver> Worker<S> {
         line_length: usize,
         chunk_rx: channel::Receiver<PuzzleChunk>,
         output_tx: channel::Sender<SolvedChunk>,
-    ) -> thread::JoinHandle<()> {
+        cancel: CancelToken,
+        budget: Budget,
+        alphabet: Alphabet,
+    ) -> thread::JoinHandle<Result<()>> {
         thread::spawn(move || {
             let mut state = solver.make_state();
             let worker = Worker {
                 solver,
                 line_length,
+                budget,
+                alphabet,
             };
             for chunk in chunk_rx.iter() {
-                let solved = worker.process_chunk(chunk, &mut state);
-                output_tx.send(solved).expect("Failed to send output chunk");
+                if cancel.is_cancelled() {
+                    break;
+                }
+                let solved = match worker.process_chunk(chunk, &mut state) {
+                    Ok(solved) => solved,
+                    Err(err) => {
+                        // Stop the reader and the other workers as well
+                        cancel.cancel();
+                        return Err(err);
+                    }
+                };
+                // The writer only hangs up early if it failed, and reports that itself
+                if output_tx.send(solved).is_err() {
+                    break;
+                }
             }
+            Ok(())
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn_multiple(
         solver: S,
         line_length: usize,
         chunk_rx: channel::Receiver<PuzzleChunk>,
         output_tx: channel::Sender<SolvedChunk>,
+        cancel: CancelToken,
+        budget: Budget,
+        alphabet: Alphabet,
         num_workers: usize,
-    ) -> Vec<thread::JoinHandle<()>> {
+    ) -> Vec<thread::JoinHandle<Result<()>>> {
         let mut handles = Vec::new();
         for _ in 0..num_workers {
             let handle = Worker::spawn(
@@ -90,6 +129,9 @@ impl<S: Solver> Worker<S> {
                 line_length,
                 chunk_rx.clone(),
                 output_tx.clone(),
+                cancel.clone(),
+                budget,
+                alphabet,
             );
             handles.push(handle);
         }
@@ -1,48 +1,49 @@
-use std::{
-    collections::BTreeMap,
-    fs::{File, OpenOptions},
-    io::{BufWriter, Write},
-    path::PathBuf,
-    thread,
-};
+use std::{collections::BTreeMap, io::Write, thread};
 
 use crossbeam::channel;
 use sha2::{Digest, Sha256};
 
-use crate::types::{ChunkStats, SolvedChunk};
+use crate::{
+    error::Result,
+    types::{ChunkStats, SolvedChunk},
+};
+
+/// Called after each chunk is written with the chunk id and the running totals.
+pub type ChunkCallback = Box<dyn FnMut(usize, &ChunkStats) + Send>;
 
 pub struct Writer {
-    writer: Option<BufWriter<File>>,
+    writer: Option<Box<dyn Write + Send>>,
     hasher: Sha256,
     stats: ChunkStats,
     next_id: usize,
     no_hash: bool,
-    verbose: bool,
+    on_chunk: Option<ChunkCallback>,
 }
 
 impl Writer {
-    fn append_chunk(&mut self, chunk: SolvedChunk) {
+    fn append_chunk(&mut self, chunk: SolvedChunk) -> Result<()> {
         self.stats.add(&chunk.stats);
-        if self.verbose {
-            println!("Processed chunk ID: {}, {}", chunk.id, self.stats.puzzles);
+        if let Some(on_chunk) = self.on_chunk.as_mut() {
+            on_chunk(chunk.id, &self.stats);
         }
         if let Some(w) = self.writer.as_mut() {
-            w.write_all(&chunk.data).expect("Failed to write chunk");
+            w.write_all(&chunk.data)?;
         }
         if !self.no_hash {
             self.hasher.update(&chunk.data);
         }
         self.next_id += 1;
+        Ok(())
     }
 
-    fn process(&mut self, output_rx: channel::Receiver<SolvedChunk>) {
+    fn process(&mut self, output_rx: channel::Receiver<SolvedChunk>) -> Result<()> {
         let mut pending_chunks = BTreeMap::new();
 
         for chunk in output_rx.iter() {
             if chunk.id == self.next_id {
-                self.append_chunk(chunk);
+                self.append_chunk(chunk)?;
                 while let Some(next) = pending_chunks.remove(&self.next_id) {
-                    self.append_chunk(next);
+                    self.append_chunk(next)?;
                 }
             } else {
                 pending_chunks.insert(chunk.id, chunk);
@@ -50,46 +51,37 @@ impl Writer {
         }
 
         if let Some(w) = self.writer.as_mut() {
-            w.flush().expect("Failed to flush writer");
+            w.flush()?;
         }
+        Ok(())
     }
 
+    /// Spawns the writer thread, which reorders chunks by id before writing
+    /// them to `sink` (if any) and hashing them.
     pub fn spawn(
         output_rx: channel::Receiver<SolvedChunk>,
-        outfile: Option<PathBuf>,
+        sink: Option<Box<dyn Write + Send>>,
         no_hash: bool,
-        verbose: bool,
-    ) -> thread::JoinHandle<(Option<String>, ChunkStats)> {
+        on_chunk: Option<ChunkCallback>,
+    ) -> thread::JoinHandle<Result<(Option<String>, ChunkStats)>> {
         thread::spawn(move || {
-            let writer = outfile
-                .as_ref()
-                .map(|path| {
-                    OpenOptions::new()
-                        .write(true)
-                        .create(true)
-                        .truncate(true)
-                        .open(path)
-                        .expect("Failed to open output file")
-                })
-                .map(BufWriter::new);
-
             let mut writer = Writer {
-                writer,
+                writer: sink,
                 hasher: Sha256::new(),
                 stats: ChunkStats::default(),
                 next_id: 0,
                 no_hash,
-                verbose,
+                on_chunk,
             };
 
-            writer.process(output_rx);
+            writer.process(output_rx)?;
 
             let hash = match no_hash {
                 true => None,
                 false => Some(format!("{:x}", writer.hasher.finalize())),
             };
 
-            (hash, writer.stats)
+            Ok((hash, writer.stats))
         })
     }
 }